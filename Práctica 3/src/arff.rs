@@ -0,0 +1,455 @@
+// Lectura de ficheros ARFF (formato de Weka)
+//
+// Se interpretan las secciones @relation, @attribute y @data. Los
+// atributos pueden ser numéricos (numeric, real, integer), nominales
+// ({v1, v2, ...}) o cadenas. Los valores pueden ir entre comillas
+// simples o dobles (y contener entonces comas o espacios). Los valores
+// ausentes (? o celda vacía, sin comillas, y en los atributos
+// numéricos también NA o NaN, como en los CSV) se conservan como tales
+// para que sea quien use los datos quien decida qué hacer con ellos.
+// Los atributos date y relational no están soportados.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+
+// Tipo de un atributo según la cabecera
+#[derive(Clone, Debug, PartialEq)]
+pub enum TipoAtributo {
+    Numerico,
+    Nominal(Vec<String>),
+    Cadena,
+}
+
+// Atributo declarado en la cabecera
+#[derive(Clone, Debug)]
+pub struct Atributo {
+    pub nombre: String,
+    pub tipo: TipoAtributo,
+}
+
+// Valor de una celda de la sección @data. Los nominales se guardan
+// como índice dentro de los valores declarados en la cabecera
+#[derive(Clone, Debug, PartialEq)]
+pub enum Valor {
+    Numerico(f32),
+    Nominal(usize),
+    Cadena(String),
+    Ausente,
+}
+
+// Contenido completo de un fichero ARFF. Para cada instancia se
+// guarda también la línea del fichero en la que aparece
+pub struct Arff {
+    pub relacion: String,
+    pub atributos: Vec<Atributo>,
+    pub instancias: Vec<Vec<Valor>>,
+    pub lineas: Vec<usize>,
+}
+
+// Error de lectura, indicando la línea (y la columna, si se trata de
+// un valor concreto) del fichero que lo provoca
+#[derive(Debug)]
+pub struct ErrorArff {
+    pub linea: usize,
+    pub columna: Option<usize>,
+    pub mensaje: String,
+}
+
+impl fmt::Display for ErrorArff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.columna {
+            Some(columna) => write!(f, "ARFF, línea {}, columna {}: {}",
+                                    self.linea, columna, self.mensaje),
+            None => write!(f, "ARFF, línea {}: {}", self.linea,
+                           self.mensaje),
+        }
+    }
+}
+
+impl Error for ErrorArff {}
+
+fn error_arff(linea: usize, mensaje: String) -> Box<dyn Error> {
+    Box::new(ErrorArff { linea, columna: None, mensaje })
+}
+
+fn error_arff_columna(linea: usize, columna: usize, mensaje: String)
+                      -> Box<dyn Error> {
+    Box::new(ErrorArff { linea, columna: Some(columna), mensaje })
+}
+
+// Lee y procesa un fichero ARFF
+
+pub fn leer_arff(path: &str) -> Result<Arff, Box<dyn Error>> {
+    let contenido = fs::read_to_string(path)?;
+    procesar_arff(&contenido)
+}
+
+// Procesa el contenido de un fichero ARFF ya cargado en memoria
+
+pub fn procesar_arff(contenido: &str) -> Result<Arff, Box<dyn Error>> {
+    let mut relacion = String::new();
+    let mut atributos: Vec<Atributo> = Vec::new();
+    let mut instancias: Vec<Vec<Valor>> = Vec::new();
+    let mut lineas: Vec<usize> = Vec::new();
+    let mut en_datos = false;
+
+    for (indice, linea_original) in contenido.lines().enumerate() {
+        let n_linea = indice + 1;
+        let linea = linea_original.trim();
+
+        // Líneas vacías y comentarios
+        if linea.is_empty() || linea.starts_with('%') {
+            continue;
+        }
+
+        if en_datos {
+            instancias.push(procesar_instancia(linea, &atributos, n_linea)?);
+            lineas.push(n_linea);
+            continue;
+        }
+
+        let (directiva, resto) = separar_primer_token(linea);
+        match directiva.to_lowercase().as_str() {
+            "@relation" => {
+                relacion = quitar_comillas(resto.trim()).to_string();
+            }
+            "@attribute" => {
+                atributos.push(procesar_atributo(resto, n_linea)?);
+            }
+            "@data" => {
+                if atributos.is_empty() {
+                    return Err(error_arff(
+                        n_linea,
+                        "sección @data sin atributos declarados".to_string()));
+                }
+                en_datos = true;
+            }
+            _ => {
+                return Err(error_arff(
+                    n_linea,
+                    format!("directiva desconocida '{}'", directiva)));
+            }
+        }
+    }
+
+    if !en_datos {
+        return Err(error_arff(contenido.lines().count(),
+                              "no se encontró la sección @data".to_string()));
+    }
+
+    Ok(Arff { relacion, atributos, instancias, lineas })
+}
+
+// Procesa la declaración de un atributo: nombre (posiblemente entre
+// comillas) seguido de su tipo
+
+fn procesar_atributo(declaracion: &str, n_linea: usize)
+                     -> Result<Atributo, Box<dyn Error>> {
+    let declaracion = declaracion.trim();
+
+    let (nombre, tipo) = if declaracion.starts_with('\'') ||
+        declaracion.starts_with('"') {
+        let comilla = declaracion.chars().next().unwrap();
+        match declaracion[1..].find(comilla) {
+            Some(fin) => (&declaracion[1..fin + 1], &declaracion[fin + 2..]),
+            None => return Err(error_arff(
+                n_linea,
+                "nombre de atributo sin cerrar comillas".to_string())),
+        }
+    } else {
+        separar_primer_token(declaracion)
+    };
+
+    let tipo = tipo.trim();
+    if nombre.is_empty() || tipo.is_empty() {
+        return Err(error_arff(n_linea,
+                              "declaración de atributo incompleta".to_string()));
+    }
+
+    let tipo = if tipo.starts_with('{') {
+        if !tipo.ends_with('}') {
+            return Err(error_arff(
+                n_linea,
+                format!("tipo nominal sin cerrar en '{}'", nombre)));
+        }
+        let valores: Vec<String> =
+            separar_valores(&tipo[1..tipo.len() - 1], n_linea)?
+            .into_iter()
+            .map(|(v, _)| v)
+            .filter(|v| !v.is_empty())
+            .collect();
+        if valores.is_empty() {
+            return Err(error_arff(
+                n_linea,
+                format!("atributo nominal '{}' sin valores", nombre)));
+        }
+        TipoAtributo::Nominal(valores)
+    } else {
+        let (nombre_tipo, _) = separar_primer_token(tipo);
+        match nombre_tipo.to_lowercase().as_str() {
+            "numeric" | "real" | "integer" => TipoAtributo::Numerico,
+            "string" => TipoAtributo::Cadena,
+            "date" | "relational" => return Err(error_arff(
+                n_linea,
+                format!("los atributos de tipo {} no están soportados \
+                         ('{}')", nombre_tipo.to_lowercase(), nombre))),
+            _ => return Err(error_arff(
+                n_linea,
+                format!("tipo '{}' no soportado para '{}'", tipo, nombre))),
+        }
+    };
+
+    Ok(Atributo { nombre: nombre.to_string(), tipo })
+}
+
+// Procesa una línea de la sección @data
+
+fn procesar_instancia(linea: &str, atributos: &[Atributo], n_linea: usize)
+                      -> Result<Vec<Valor>, Box<dyn Error>> {
+    if linea.starts_with('{') {
+        return Err(error_arff(n_linea,
+                              "formato ARFF disperso no soportado".to_string()));
+    }
+
+    let campos = separar_valores(linea, n_linea)?;
+    if campos.len() != atributos.len() {
+        return Err(error_arff(
+            n_linea,
+            format!("se esperaban {} valores y se encontraron {}",
+                    atributos.len(), campos.len())));
+    }
+
+    let mut instancia = Vec::with_capacity(atributos.len());
+    for (columna, ((campo, entre_comillas), atributo)) in campos.iter()
+        .zip(atributos.iter()).enumerate() {
+        if !entre_comillas && (campo.is_empty() || campo == "?") {
+            instancia.push(Valor::Ausente);
+            continue;
+        }
+        let campo = campo.as_str();
+
+        let valor = match atributo.tipo {
+            TipoAtributo::Numerico => match campo.parse::<f32>() {
+                Ok(v) if v.is_nan() => Valor::Ausente,
+                Ok(v) => Valor::Numerico(v),
+                Err(_) if !entre_comillas &&
+                    campo.eq_ignore_ascii_case("na") => Valor::Ausente,
+                Err(_) => return Err(error_arff_columna(
+                    n_linea, columna + 1,
+                    format!("valor '{}' no numérico para '{}'",
+                            campo, atributo.nombre))),
+            },
+            TipoAtributo::Nominal(ref valores) => {
+                match valores.iter().position(|v| v == campo) {
+                    Some(posicion) => Valor::Nominal(posicion),
+                    None => return Err(error_arff_columna(
+                        n_linea, columna + 1,
+                        format!("valor '{}' no declarado para '{}'",
+                                campo, atributo.nombre))),
+                }
+            }
+            TipoAtributo::Cadena => Valor::Cadena(campo.to_string()),
+        };
+        instancia.push(valor);
+    }
+
+    Ok(instancia)
+}
+
+// Separa los valores de una línea por comas. Los valores entre comillas
+// simples o dobles pueden contener comas y se devuelven sin ellas (con
+// \ para escapar una comilla), junto a si estaban entre comillas
+
+fn separar_valores(linea: &str, n_linea: usize)
+                   -> Result<Vec<(String, bool)>, Box<dyn Error>> {
+    let mut valores: Vec<(String, bool)> = Vec::new();
+    let mut caracteres = linea.chars().peekable();
+
+    loop {
+        while caracteres.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut valor = String::new();
+        let entre_comillas = match caracteres.peek() {
+            Some(&comilla) if comilla == '\'' || comilla == '"' => {
+                caracteres.next();
+                loop {
+                    match caracteres.next() {
+                        Some('\\') => {
+                            if let Some(c) = caracteres.next() {
+                                valor.push(c);
+                            }
+                        }
+                        Some(c) if c == comilla => break,
+                        Some(c) => valor.push(c),
+                        None => return Err(error_arff_columna(
+                            n_linea, valores.len() + 1,
+                            "valor sin cerrar comillas".to_string())),
+                    }
+                }
+                while caracteres.next_if(|c| c.is_whitespace()).is_some() {}
+                true
+            }
+            _ => {
+                while let Some(c) = caracteres.next_if(|&c| c != ',') {
+                    valor.push(c);
+                }
+                valor.truncate(valor.trim_end().len());
+                false
+            }
+        };
+        valores.push((valor, entre_comillas));
+
+        match caracteres.next() {
+            Some(',') => continue,
+            None => break,
+            Some(_) => return Err(error_arff_columna(
+                n_linea, valores.len(),
+                "texto tras las comillas de un valor".to_string())),
+        }
+    }
+
+    Ok(valores)
+}
+
+// Separa el primer token (hasta el primer espacio o tabulador) del
+// resto de la línea
+
+fn separar_primer_token(linea: &str) -> (&str, &str) {
+    match linea.find(char::is_whitespace) {
+        Some(posicion) => (&linea[..posicion], &linea[posicion..]),
+        None => (linea, ""),
+    }
+}
+
+fn quitar_comillas(texto: &str) -> &str {
+    let bytes = texto.as_bytes();
+    if bytes.len() >= 2 &&
+        (bytes[0] == b'\'' || bytes[0] == b'"') &&
+        bytes[bytes.len() - 1] == bytes[0] {
+        &texto[1..texto.len() - 1]
+    } else {
+        texto
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Línea y columna del error al procesar el contenido
+    fn posicion_error(contenido: &str) -> (usize, Option<usize>) {
+        let err = procesar_arff(contenido).err().expect("se esperaba error");
+        let err = err.downcast_ref::<ErrorArff>().expect("no es ErrorArff");
+        (err.linea, err.columna)
+    }
+
+    #[test]
+    fn cabecera_y_datos() {
+        let arff = procesar_arff("% comentario\n\
+                                  @relation 'mi relación'\n\
+                                  @attribute 'ancho total' numeric\n\
+                                  @attribute color {rojo, 'azul claro'}\n\
+                                  @attribute nota string\n\
+                                  @attribute clase {\"a\", b}\n\
+                                  \n\
+                                  @data\n\
+                                  1.5, rojo, 'hola, mundo', a\n\
+                                  -2, 'azul claro', \"dice \\\"sí\\\"\", b\n")
+            .unwrap();
+
+        assert_eq!(arff.relacion, "mi relación");
+        let nombres: Vec<&str> = arff.atributos.iter()
+            .map(|a| a.nombre.as_str())
+            .collect();
+        assert_eq!(nombres, ["ancho total", "color", "nota", "clase"]);
+        assert_eq!(arff.atributos[1].tipo,
+                   TipoAtributo::Nominal(vec!["rojo".to_string(),
+                                              "azul claro".to_string()]));
+        assert_eq!(arff.atributos[3].tipo,
+                   TipoAtributo::Nominal(vec!["a".to_string(),
+                                              "b".to_string()]));
+
+        assert_eq!(arff.instancias, vec![
+            vec![Valor::Numerico(1.5), Valor::Nominal(0),
+                 Valor::Cadena("hola, mundo".to_string()), Valor::Nominal(0)],
+            vec![Valor::Numerico(-2.0), Valor::Nominal(1),
+                 Valor::Cadena("dice \"sí\"".to_string()), Valor::Nominal(1)],
+        ]);
+        assert_eq!(arff.lineas, vec![9, 10]);
+    }
+
+    #[test]
+    fn valores_ausentes() {
+        let arff = procesar_arff("@relation r\n\
+                                  @attribute x numeric\n\
+                                  @attribute s string\n\
+                                  @attribute c {a, b}\n\
+                                  @data\n\
+                                  ?, '?', ?\n\
+                                  , '', a\n\
+                                  NA, NA, b\n\
+                                  -nan, x, a\n")
+            .unwrap();
+
+        // Un ? o una celda vacía entre comillas es un valor
+        assert_eq!(arff.instancias, vec![
+            vec![Valor::Ausente, Valor::Cadena("?".to_string()),
+                 Valor::Ausente],
+            vec![Valor::Ausente, Valor::Cadena(String::new()),
+                 Valor::Nominal(0)],
+            // En los numéricos NA y NaN también son ausentes
+            vec![Valor::Ausente, Valor::Cadena("NA".to_string()),
+                 Valor::Nominal(1)],
+            vec![Valor::Ausente, Valor::Cadena("x".to_string()),
+                 Valor::Nominal(0)],
+        ]);
+        assert!(procesar_arff("@relation r\n@attribute x numeric\n\
+                               @data\n'NA'\n").is_err());
+    }
+
+    #[test]
+    fn tipos_no_soportados() {
+        let cabecera = "@relation r\n@attribute x numeric\n";
+        assert_eq!(posicion_error(&format!("{}@attribute d date\n@data\n",
+                                           cabecera)),
+                   (3, None));
+        assert_eq!(posicion_error(&format!(
+            "{}@attribute f date \"yyyy-MM-dd\"\n@data\n", cabecera)),
+                   (3, None));
+        assert_eq!(posicion_error(&format!(
+            "{}@attribute r relational\n@data\n", cabecera)), (3, None));
+        // Formato disperso
+        assert_eq!(posicion_error(&format!("{}@data\n{{0 1.5}}\n",
+                                           cabecera)),
+                   (4, None));
+    }
+
+    #[test]
+    fn posicion_de_los_errores() {
+        let cabecera = "@relation r\n\
+                        @attribute x numeric\n\
+                        @attribute c {a, b}\n\
+                        @data\n";
+
+        // Valor no numérico y valor nominal no declarado
+        assert_eq!(posicion_error(&format!("{}1, a\n% otra\nuno, b\n",
+                                           cabecera)),
+                   (7, Some(1)));
+        assert_eq!(posicion_error(&format!("{}1, c\n", cabecera)),
+                   (5, Some(2)));
+        // Comillas sin cerrar y texto tras ellas
+        assert_eq!(posicion_error(&format!("{}1, 'a\n", cabecera)),
+                   (5, Some(2)));
+        assert_eq!(posicion_error(&format!("{}'1'x, a\n", cabecera)),
+                   (5, Some(1)));
+        // Número de valores incorrecto
+        assert_eq!(posicion_error(&format!("{}1, a, b\n", cabecera)),
+                   (5, None));
+        // Directiva desconocida y falta de @data
+        assert_eq!(posicion_error("@relation r\n@atributo x numeric\n"),
+                   (2, None));
+        assert_eq!(posicion_error("@relation r\n@attribute x numeric\n"),
+                   (2, None));
+    }
+}
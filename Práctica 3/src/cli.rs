@@ -0,0 +1,564 @@
+// Interfaz de línea de órdenes
+//
+// mh_p1 [ejecutar] [opciones]   Ejecuta los algoritmos indicados
+// mh_p1 algoritmos              Lista los algoritmos disponibles
+// mh_p1 ayuda                   Muestra la ayuda
+// mh_p1 <semilla>               Forma antigua: todo con esa semilla
+
+use std::collections::HashSet;
+
+use crate::datos::PoliticaAusentes;
+use crate::distancia::Distancia;
+use crate::escalado::TipoEscalado;
+use crate::indice::TipoIndice;
+use crate::knn::Knn;
+use crate::objetivo::Objetivo;
+use crate::planificador::procesadores;
+use crate::Algoritmo;
+
+const DATOS_POR_DEFECTO: [&str; 3] = [
+    "../data/colposcopy.csv",
+    "../data/ionosphere.csv",
+    "../data/texture.csv",
+];
+const PARTICIONES_POR_DEFECTO: usize = 5;
+const SEMILLA_POR_DEFECTO: u64 = 4;
+// Cada semilla repite todo el experimento, así que más de estas sólo
+// puede ser un error al escribir un rango
+const MAXIMO_SEMILLAS: u64 = 10_000;
+
+pub const AYUDA: &str = "\
+Uso:
+  mh_p1 [ejecutar] [opciones]
+  mh_p1 algoritmos
+  mh_p1 ayuda
+  mh_p1 <semilla>
+
+Opciones de ejecutar:
+  -d, --datos RUTA[,RUTA...]    Ficheros CSV o ARFF a analizar (se puede
+                                repetir). Por defecto colposcopy,
+                                ionosphere y texture de ../data
+  -a, --algoritmos LISTA        Algoritmos a ejecutar, separados por comas
+                                (ver 'mh_p1 algoritmos'). Por defecto todos
+  -k, --particiones K           Número de particiones (por defecto 5)
+  -r, --repeticiones N          Repite la validación cruzada con N
+                                particiones distintas (por ejemplo -k 2
+                                -r 5 para 5x2). Por defecto 1
+      --sin-mezclar             Reparte las muestras en el orden del fichero
+                                en lugar de mezclarlas con la semilla
+  -s, --semillas LISTA          Semillas separadas por comas, admitiendo
+                                rangos INICIO-FIN (por ejemplo 1-10,42).
+                                Por defecto 4
+      --ausentes POLITICA       Valores ausentes: error, eliminar, media o
+                                mediana (por defecto error)
+  -o, --exportar FICHERO        Guarda cada resultado en FICHERO, en CSV o
+                                JSON Lines según su extensión (.csv o
+                                .jsonl)
+      --informe FICHERO         Genera las tablas de resultados de cada
+                                conjunto de datos y la comparativa global,
+                                en LaTeX (.tex) o Markdown (.md)
+      --estadistica             Compara los algoritmos con los tests de
+                                Friedman (con post-hoc de Holm y Finner) y
+                                de Wilcoxon sobre la función objetivo
+      --matriz-confusion        Muestra la matriz de confusión y las
+                                métricas de cada clase en validación
+      --predicciones            Muestra la clase predicha para cada muestra
+                                de validación
+  -f, --objetivo FUNCION        Función objetivo que maximizan los
+                                algoritmos: combinación de los términos
+                                clas, bal, f1, kappa, mcc, coste y red (por
+                                ejemplo 0.4*bal+0.4*f1+0.2*red). Un término
+                                solo, como bal, se combina con red a partes
+                                iguales. Por defecto clas
+      --costes ETIQUETA=COSTE[,...]
+                                Coste de fallar en cada clase para el
+                                término coste (por defecto 1)
+  -n, --vecinos K               Número de vecinos del k-NN, en validación
+                                y en la función objetivo (por defecto 1)
+      --votacion TIPO           Votación del k-NN: mayoria o distancia
+                                (ponderada por la inversa de la distancia).
+                                Por defecto mayoria
+      --desempate REGLA         Clase elegida si hay empate en la votación:
+                                cercano (la del vecino más cercano) o menor
+                                (la primera en el fichero). Por defecto
+                                cercano
+  -m, --distancia TIPO          Distancia del clasificador y de RELIEF:
+                                euclidea, euclidea2 (al cuadrado),
+                                manhattan, minkowski:P, chebyshev o coseno.
+                                Por defecto euclidea
+      --indice TIPO             Búsqueda de los vecinos: ninguno
+                                (exhaustiva) o kd (árbol k-d, para
+                                conjuntos grandes; no admite la distancia
+                                coseno). Por defecto ninguno. Al entrenar
+                                sólo se usa si las distancias entre las
+                                muestras no caben en memoria (más de
+                                1 GiB) o no son aditivas
+  -t, --trabajos N              Trabajos (algoritmo en una partición de un
+                                conjunto de datos) que se ejecutan a la
+                                vez. Los resultados se muestran en el mismo
+                                orden. Por defecto, tantos como
+                                procesadores. Se ejecutan menos si las
+                                distancias entre las muestras de todos no
+                                caben a la vez en memoria
+  -j, --hilos N                 Hilos con los que cada trabajo evalúa las
+                                poblaciones de los algoritmos evolutivos
+                                (los resultados no dependen de su número).
+                                Por defecto, los procesadores repartidos
+                                entre los trabajos. Se usan menos si las
+                                sumas de un trabajo no caben en memoria
+  -e, --escalado TIPO           Escalado de los atributos: minmax, zscore,
+                                robusto, maxabs o ninguno (por defecto
+                                minmax)
+      --escalado-global         Ajusta el escalado con todos los datos
+                                (validación incluida) en lugar de con el
+                                entrenamiento de cada partición";
+
+// Parámetros de una ejecución
+pub struct Configuracion {
+    pub datos: Vec<String>,
+    pub algoritmos: Vec<Algoritmo>,
+    pub particiones: usize,
+    pub repeticiones: usize,
+    pub mezclar: bool,
+    pub semillas: Vec<u64>,
+    pub politica_ausentes: PoliticaAusentes,
+    pub objetivo: Objetivo,
+    pub knn: Knn,
+    pub trabajos: usize,
+    // Sin indicar, se reparten los procesadores entre los trabajos
+    pub hilos: Option<usize>,
+    pub exportar: Option<String>,
+    pub informe: Option<String>,
+    pub estadistica: bool,
+    pub mostrar_matriz: bool,
+    pub mostrar_predicciones: bool,
+    pub escalado: TipoEscalado,
+    pub escalado_global: bool,
+}
+
+impl Default for Configuracion {
+    fn default() -> Configuracion {
+        Configuracion {
+            datos: DATOS_POR_DEFECTO.iter().map(|d| d.to_string()).collect(),
+            algoritmos: Algoritmo::TODOS.to_vec(),
+            particiones: PARTICIONES_POR_DEFECTO,
+            repeticiones: 1,
+            mezclar: true,
+            semillas: vec![SEMILLA_POR_DEFECTO],
+            politica_ausentes: PoliticaAusentes::Error,
+            objetivo: Objetivo::default(),
+            knn: Knn::default(),
+            trabajos: procesadores(),
+            hilos: None,
+            exportar: None,
+            informe: None,
+            estadistica: false,
+            mostrar_matriz: false,
+            mostrar_predicciones: false,
+            escalado: TipoEscalado::MinMax,
+            escalado_global: false,
+        }
+    }
+}
+
+// Orden indicada en la línea de órdenes
+pub enum Orden {
+    Ejecutar(Box<Configuracion>),
+    ListarAlgoritmos,
+    Ayuda,
+}
+
+// Procesa los argumentos (sin el nombre del programa)
+
+pub fn procesar_argumentos(args: &[String]) -> Result<Orden, String> {
+    let mut restantes = args;
+
+    match args.first().map(|a| a.as_str()) {
+        Some("algoritmos") => return Ok(Orden::ListarAlgoritmos),
+        Some("ayuda") | Some("-h") | Some("--help") => return Ok(Orden::Ayuda),
+        Some("ejecutar") => restantes = &args[1..],
+        Some(semilla) if args.len() == 1 && !semilla.starts_with('-') => {
+            let semilla = leer_numero(semilla, "semilla")?;
+            return Ok(Orden::Ejecutar(Box::new(Configuracion {
+                semillas: vec![semilla],
+                ..Configuracion::default()
+            })));
+        }
+        _ => (),
+    }
+
+    let mut configuracion = Configuracion::default();
+    let mut datos: Vec<String> = Vec::new();
+    let mut costes: Vec<(String, f32)> = Vec::new();
+
+    let mut i = 0;
+    while i < restantes.len() {
+        // Se admite tanto --opcion valor como --opcion=valor
+        let (opcion, valor_en_linea) = match restantes[i].find('=') {
+            Some(pos) if restantes[i].starts_with("--") =>
+                (&restantes[i][..pos], Some(restantes[i][pos + 1..].to_string())),
+            _ => (restantes[i].as_str(), None),
+        };
+
+        // Opciones sin valor
+        match opcion {
+            "--predicciones" => {
+                configuracion.mostrar_predicciones = true;
+                i += 1;
+                continue;
+            }
+            "--escalado-global" => {
+                configuracion.escalado_global = true;
+                i += 1;
+                continue;
+            }
+            "--matriz-confusion" => {
+                configuracion.mostrar_matriz = true;
+                i += 1;
+                continue;
+            }
+            "--estadistica" => {
+                configuracion.estadistica = true;
+                i += 1;
+                continue;
+            }
+            "--sin-mezclar" => {
+                configuracion.mezclar = false;
+                i += 1;
+                continue;
+            }
+            _ => (),
+        }
+
+        let valor = match valor_en_linea {
+            Some(valor) => valor,
+            None => {
+                i += 1;
+                match restantes.get(i) {
+                    Some(valor) => valor.clone(),
+                    None => return Err(format!("falta el valor de {}", opcion)),
+                }
+            }
+        };
+
+        match opcion {
+            "-d" | "--datos" => {
+                datos.extend(separar_lista(&valor).map(|d| d.to_string()));
+            }
+            "-a" | "--algoritmos" => {
+                configuracion.algoritmos = leer_algoritmos(&valor)?;
+            }
+            "-k" | "--particiones" => {
+                configuracion.particiones =
+                    leer_numero(&valor, "número de particiones")?;
+                if configuracion.particiones < 2 {
+                    return Err("se necesitan al menos 2 particiones"
+                               .to_string());
+                }
+            }
+            "-r" | "--repeticiones" => {
+                configuracion.repeticiones =
+                    leer_numero(&valor, "número de repeticiones")?;
+                if configuracion.repeticiones == 0 {
+                    return Err("se necesita al menos 1 repetición"
+                               .to_string());
+                }
+            }
+            "-s" | "--semillas" => {
+                configuracion.semillas = leer_semillas(&valor)?;
+            }
+            "-f" | "--objetivo" => {
+                configuracion.objetivo = valor.parse()?;
+            }
+            "--costes" => {
+                costes = leer_costes(&valor)?;
+            }
+            "-n" | "--vecinos" => {
+                configuracion.knn.k =
+                    leer_numero(&valor, "número de vecinos")?;
+                if configuracion.knn.k == 0 {
+                    return Err("se necesita al menos 1 vecino".to_string());
+                }
+            }
+            "-m" | "--distancia" => {
+                configuracion.knn.distancia = valor.parse()?;
+            }
+            "--indice" => {
+                configuracion.knn.indice = valor.parse()?;
+            }
+            "--votacion" => {
+                configuracion.knn.votacion = valor.parse()?;
+            }
+            "--desempate" => {
+                configuracion.knn.desempate = valor.parse()?;
+            }
+            "-t" | "--trabajos" => {
+                configuracion.trabajos =
+                    leer_numero(&valor, "número de trabajos")?;
+                if configuracion.trabajos == 0 {
+                    return Err("se necesita al menos 1 trabajo".to_string());
+                }
+            }
+            "-j" | "--hilos" => {
+                let hilos = leer_numero(&valor, "número de hilos")?;
+                if hilos == 0 {
+                    return Err("se necesita al menos 1 hilo".to_string());
+                }
+                configuracion.hilos = Some(hilos);
+            }
+            "-e" | "--escalado" => {
+                configuracion.escalado = valor.parse()?;
+            }
+            "-o" | "--exportar" => {
+                configuracion.exportar = Some(valor);
+            }
+            "--informe" => {
+                configuracion.informe = Some(valor);
+            }
+            "--ausentes" => {
+                configuracion.politica_ausentes = valor.parse()?;
+            }
+            _ => return Err(format!("opción desconocida '{}'", opcion)),
+        }
+        i += 1;
+    }
+
+    if !datos.is_empty() {
+        configuracion.datos = datos;
+    }
+    if configuracion.algoritmos.is_empty() {
+        return Err("no se ha indicado ningún algoritmo".to_string());
+    }
+    if configuracion.repeticiones > 1 && !configuracion.mezclar {
+        return Err("sin mezclar todas las repeticiones tendrían las mismas \
+                    particiones".to_string());
+    }
+    if configuracion.semillas.is_empty() {
+        return Err("no se ha indicado ninguna semilla".to_string());
+    }
+    if configuracion.knn.indice == TipoIndice::ArbolKd &&
+        configuracion.knn.distancia == Distancia::Coseno {
+        return Err("el árbol k-d no admite la distancia coseno"
+                   .to_string());
+    }
+    if !costes.is_empty() {
+        if !configuracion.objetivo.usa_costes() {
+            return Err("los costes sólo se usan con el término coste de la \
+                        función objetivo".to_string());
+        }
+        configuracion.objetivo.fijar_costes(costes);
+    }
+
+    Ok(Orden::Ejecutar(Box::new(configuracion)))
+}
+
+// Lista de algoritmos. Además de sus nombres se admiten los grupos
+// agg, age, am, de y todos. Se respeta el orden indicado y se omiten
+// los repetidos
+
+fn leer_algoritmos(lista: &str) -> Result<Vec<Algoritmo>, String> {
+    let mut algoritmos: Vec<Algoritmo> = Vec::new();
+
+    for nombre in separar_lista(lista) {
+        let nombre = nombre.to_lowercase();
+        let seleccion: Vec<Algoritmo> = if nombre == "todos" {
+            Algoritmo::TODOS.to_vec()
+        } else if let Some(algoritmo) = Algoritmo::desde_nombre(&nombre) {
+            vec![algoritmo]
+        } else {
+            let grupo: Vec<Algoritmo> = Algoritmo::TODOS.iter()
+                .filter(|a| a.nombre().starts_with(&format!("{}-", nombre)))
+                .cloned()
+                .collect();
+            if grupo.is_empty() {
+                return Err(format!("algoritmo desconocido '{}'", nombre));
+            }
+            grupo
+        };
+
+        for algoritmo in seleccion {
+            if !algoritmos.contains(&algoritmo) {
+                algoritmos.push(algoritmo);
+            }
+        }
+    }
+
+    Ok(algoritmos)
+}
+
+// Lista de semillas: valores sueltos o rangos INICIO-FIN (ambos
+// incluidos). Cada semilla sólo puede aparecer una vez, y no puede
+// haber más de MAXIMO_SEMILLAS
+
+fn leer_semillas(lista: &str) -> Result<Vec<u64>, String> {
+    let mut semillas: Vec<u64> = Vec::new();
+    // Las ya leídas, para comprobar las repetidas en rangos grandes
+    let mut leidas: HashSet<u64> = HashSet::new();
+
+    for elemento in separar_lista(lista) {
+        let rango = match elemento.split_once('-') {
+            Some((inicio, fin)) => {
+                let inicio: u64 = leer_numero(inicio.trim(), "semilla")?;
+                let fin: u64 = leer_numero(fin.trim(), "semilla")?;
+                if fin < inicio {
+                    return Err(format!("rango de semillas vacío '{}'",
+                                       elemento));
+                }
+                inicio..=fin
+            }
+            None => {
+                let semilla = leer_numero(elemento, "semilla")?;
+                semilla..=semilla
+            }
+        };
+
+        // Se comprueba antes de recorrer el rango, que puede ser enorme
+        if rango.end() - rango.start() >=
+            MAXIMO_SEMILLAS - semillas.len() as u64 {
+            return Err(format!("demasiadas semillas, como mucho se \
+                                admiten {}", MAXIMO_SEMILLAS));
+        }
+
+        for semilla in rango {
+            if !leidas.insert(semilla) {
+                return Err(format!("semilla repetida: {}", semilla));
+            }
+            semillas.push(semilla);
+        }
+    }
+
+    Ok(semillas)
+}
+
+// Costes de cada clase: ETIQUETA=COSTE, con costes positivos
+
+fn leer_costes(lista: &str) -> Result<Vec<(String, f32)>, String> {
+    let mut costes: Vec<(String, f32)> = Vec::new();
+
+    for elemento in separar_lista(lista) {
+        let (etiqueta, coste) = match elemento.rsplit_once('=') {
+            Some((etiqueta, coste)) => (etiqueta.trim(), coste.trim()),
+            None => return Err(format!("coste no válido '{}', se espera \
+                                        ETIQUETA=COSTE", elemento)),
+        };
+        let coste: f32 = leer_numero(coste, "coste")?;
+        if !(coste > 0.0 && coste.is_finite()) {
+            return Err(format!("el coste de la clase '{}' debe ser positivo",
+                               etiqueta));
+        }
+        if costes.iter().any(|(e, _)| e == etiqueta) {
+            return Err(format!("clase repetida en los costes: '{}'",
+                               etiqueta));
+        }
+        costes.push((etiqueta.to_string(), coste));
+    }
+
+    Ok(costes)
+}
+
+fn separar_lista(lista: &str) -> impl Iterator<Item = &str> {
+    lista.split(',').map(|e| e.trim()).filter(|e| !e.is_empty())
+}
+
+fn leer_numero<N: std::str::FromStr>(texto: &str, que: &str)
+                                     -> Result<N, String> {
+    texto.parse::<N>()
+        .map_err(|_| format!("{} no válido: '{}'", que, texto))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knn::{Desempate, Votacion};
+
+    fn procesar(args: &str) -> Result<Orden, String> {
+        let args: Vec<String> = args.split_whitespace()
+            .map(|a| a.to_string())
+            .collect();
+        procesar_argumentos(&args)
+    }
+
+    fn configuracion(args: &str) -> Configuracion {
+        match procesar(args) {
+            Ok(Orden::Ejecutar(configuracion)) => *configuracion,
+            Ok(_) => panic!("'{}' no es una ejecución", args),
+            Err(err) => panic!("'{}': {}", args, err),
+        }
+    }
+
+    fn error(args: &str) -> String {
+        match procesar(args) {
+            Err(err) => err,
+            Ok(_) => panic!("'{}' debería fallar", args),
+        }
+    }
+
+    #[test]
+    fn ordenes() {
+        assert!(matches!(procesar(""), Ok(Orden::Ejecutar(_))));
+        assert!(matches!(procesar("algoritmos"),
+                         Ok(Orden::ListarAlgoritmos)));
+        assert!(matches!(procesar("ayuda"), Ok(Orden::Ayuda)));
+        assert!(matches!(procesar("--help"), Ok(Orden::Ayuda)));
+
+        // Forma antigua: sólo la semilla
+        let c = configuracion("7");
+        assert_eq!(c.semillas, vec![7]);
+        assert_eq!(c.datos.len(), DATOS_POR_DEFECTO.len());
+        assert_eq!(c.algoritmos, Algoritmo::TODOS.to_vec());
+    }
+
+    #[test]
+    fn opciones() {
+        let c = configuracion("ejecutar -d a.csv,b.arff --datos=c.csv \
+                               -a relief,agg,1nn,relief -k 3 -r 2 \
+                               --ausentes mediana -s 3,1 --predicciones \
+                               -n 3 --votacion distancia --desempate menor \
+                               -m minkowski:3 \
+                               -e zscore --escalado-global \
+                               -o r.jsonl -t 2 -j 1");
+        assert_eq!(c.datos, ["a.csv", "b.arff", "c.csv"]);
+        // Los grupos se expanden y los repetidos se omiten
+        assert_eq!(c.algoritmos, vec![Algoritmo::Relief,
+                                      Algoritmo::AggAritmetico,
+                                      Algoritmo::AggBlx,
+                                      Algoritmo::Clasificador1nn]);
+        assert_eq!((c.particiones, c.repeticiones), (3, 2));
+        assert_eq!(c.politica_ausentes, PoliticaAusentes::ImputarMediana);
+        assert_eq!(c.semillas, vec![3, 1]);
+        assert!(c.mostrar_predicciones);
+        assert_eq!(c.knn.k, 3);
+        assert_eq!(c.knn.votacion, Votacion::Distancia);
+        assert_eq!(c.knn.desempate, Desempate::Menor);
+        assert_eq!(c.knn.distancia, Distancia::Minkowski(3.0));
+        assert_eq!(c.escalado, TipoEscalado::ZScore);
+        assert!(c.escalado_global);
+        assert_eq!(c.exportar.as_deref(), Some("r.jsonl"));
+        assert_eq!((c.trabajos, c.hilos), (2, Some(1)));
+    }
+
+    #[test]
+    fn errores() {
+        assert!(error("-k 1").contains("2 particiones"));
+        assert!(error("-k").contains("falta el valor"));
+        assert!(error("-a nada").contains("desconocido"));
+        assert!(error("-a ,").contains("ningún algoritmo"));
+        assert!(error("--no-existe 1").contains("desconocida"));
+        assert!(error("-r 2 --sin-mezclar").contains("mismas"));
+        assert!(error("-n 0").contains("vecino"));
+        assert!(error("--indice kd -m coseno").contains("coseno"));
+        assert!(error("--costes a=2").contains("término coste"));
+        assert!(error("-s x").contains("semilla no válido"));
+        assert!(error("-s 3,1-5").contains("repetida"));
+        assert!(error("-s 0-18446744073709551615")
+                .contains("demasiadas semillas"));
+        assert!(error("-s 5,1-10000").contains("demasiadas semillas"));
+    }
+
+    #[test]
+    fn semillas() {
+        assert_eq!(configuracion("-s 1-3,42,7-7").semillas,
+                   vec![1, 2, 3, 42, 7]);
+        assert_eq!(configuracion("-s 1-10000").semillas.len(), 10_000);
+    }
+}
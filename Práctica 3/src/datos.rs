@@ -0,0 +1,629 @@
+// Representación de los conjuntos de datos
+//
+// El número de atributos no se fija en tiempo de compilación, se
+// descubre al leer el fichero (CSV o ARFF), de forma que cualquier
+// conjunto de datos con atributos numéricos y la clase en la última
+// columna puede usarse sin recompilar.
+//
+// Las celdas vacías o con ?, NA o NaN se consideran valores ausentes
+// y se tratan según la política indicada. Un valor que no es numérico
+// ni ausente es siempre un error, indicando línea y columna. Si se
+// imputan, los ausentes se guardan como NaN y se sustituyen en cada
+// partición con la media o la mediana de su entrenamiento, igual que
+// se ajusta el escalado, para que la validación no influya en ellas.
+//
+// Los atributos de todas las muestras se guardan en una única matriz
+// contigua, fila a fila, y las clases en un vector aparte. Los
+// conjuntos de entrenamiento y validación de cada partición son vistas
+// con los índices de sus filas, sin copiar las muestras.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::arff::{self, Arff, TipoAtributo, Valor};
+use crate::etiquetas::CodificadorEtiquetas;
+
+// Muestra (fila) del conjunto de datos. Su identificador es su fila
+// en la matriz
+#[derive(Clone, Copy, Debug)]
+pub struct Muestra<'a> {
+    id: usize,
+    attributes: &'a [f32],
+    class: i32,
+}
+
+impl<'a> Muestra<'a> {
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    pub fn get_class(&self) -> i32 {
+        self.class
+    }
+
+    pub fn get_attribute(&self, index: usize) -> f32 {
+        self.attributes[index]
+    }
+
+    pub fn get_attributes(&self) -> &'a [f32] {
+        self.attributes
+    }
+}
+
+// Matriz de atributos (fila a fila) y clase de cada muestra
+#[derive(Clone, Debug)]
+pub struct Muestras {
+    num_atributos: usize,
+    atributos: Vec<f32>,
+    clases: Vec<i32>,
+}
+
+impl Muestras {
+    pub fn new(num_atributos: usize) -> Muestras {
+        Muestras { num_atributos, atributos: Vec::new(), clases: Vec::new() }
+    }
+
+    pub fn anadir(&mut self, atributos: &[f32], clase: i32) {
+        assert_eq!(atributos.len(), self.num_atributos);
+        self.atributos.extend_from_slice(atributos);
+        self.clases.push(clase);
+    }
+
+    pub fn len(&self) -> usize {
+        self.clases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clases.is_empty()
+    }
+
+    pub fn muestra(&self, fila: usize) -> Muestra<'_> {
+        Muestra {
+            id: fila,
+            attributes: &self.atributos[fila * self.num_atributos..
+                                        (fila + 1) * self.num_atributos],
+            class: self.clases[fila],
+        }
+    }
+
+    // Atributos de cada fila, para modificarlos
+    pub fn filas_mut(&mut self) -> impl Iterator<Item = &mut [f32]> {
+        self.atributos.chunks_exact_mut(self.num_atributos.max(1))
+    }
+
+    // Vista con las filas indicadas, en ese orden
+    pub fn vista<'a>(&'a self, filas: &'a [usize]) -> Vista<'a> {
+        Vista { muestras: self, filas }
+    }
+}
+
+// Subconjunto de las muestras dado por sus filas en la matriz
+#[derive(Clone, Copy, Debug)]
+pub struct Vista<'a> {
+    muestras: &'a Muestras,
+    filas: &'a [usize],
+}
+
+impl<'a> Vista<'a> {
+    pub fn len(&self) -> usize {
+        self.filas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filas.is_empty()
+    }
+
+    pub fn num_atributos(&self) -> usize {
+        self.muestras.num_atributos
+    }
+
+    // Muestra en la posición indicada de la vista
+    pub fn muestra(&self, posicion: usize) -> Muestra<'a> {
+        self.muestras.muestra(self.filas[posicion])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Muestra<'a>> + 'a {
+        let muestras = self.muestras;
+        self.filas.iter().map(move |&fila| muestras.muestra(fila))
+    }
+}
+
+// Conjunto de datos completo: nombre, cabecera, etiquetas de clase y
+// muestras
+pub struct Dataset {
+    pub nombre: String,
+    pub atributos: Vec<String>,
+    pub clases: CodificadorEtiquetas,
+    pub muestras: Muestras,
+}
+
+impl Dataset {
+    pub fn num_atributos(&self) -> usize {
+        self.atributos.len()
+    }
+}
+
+// Qué hacer con las filas que tienen valores ausentes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PoliticaAusentes {
+    Error,
+    EliminarFila,
+    ImputarMedia,
+    ImputarMediana,
+}
+
+impl FromStr for PoliticaAusentes {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<PoliticaAusentes, String> {
+        match texto {
+            "error" => Ok(PoliticaAusentes::Error),
+            "eliminar" => Ok(PoliticaAusentes::EliminarFila),
+            "media" => Ok(PoliticaAusentes::ImputarMedia),
+            "mediana" => Ok(PoliticaAusentes::ImputarMediana),
+            _ => Err(format!("política de ausentes desconocida '{}' \
+                              (error, eliminar, media, mediana)", texto)),
+        }
+    }
+}
+
+// Resumen de la carga de un fichero
+#[derive(Clone, Debug, Default)]
+pub struct ResumenCarga {
+    pub filas_leidas: usize,
+    pub filas_eliminadas: usize,
+    pub celdas_ausentes: usize,
+    pub celdas_imputadas: usize,
+}
+
+impl fmt::Display for ResumenCarga {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} filas leídas, {} eliminadas, {} celdas ausentes, \
+                   {} imputadas", self.filas_leidas, self.filas_eliminadas,
+               self.celdas_ausentes, self.celdas_imputadas)
+    }
+}
+
+// Error en un valor concreto del fichero
+#[derive(Debug)]
+pub struct ErrorCarga {
+    pub fichero: String,
+    pub linea: usize,
+    pub columna: usize,
+    pub mensaje: String,
+}
+
+impl fmt::Display for ErrorCarga {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, línea {}, columna {}: {}", self.fichero, self.linea,
+               self.columna, self.mensaje)
+    }
+}
+
+impl Error for ErrorCarga {}
+
+fn error_carga(fichero: &str, linea: usize, columna: usize, mensaje: String)
+               -> Box<dyn Error> {
+    Box::new(ErrorCarga {
+        fichero: fichero.to_string(),
+        linea,
+        columna,
+        mensaje,
+    })
+}
+
+// Fila tal y como se ha leído del fichero, antes de tratar los
+// valores ausentes
+struct FilaLeida {
+    linea: usize,
+    valores: Vec<Option<f32>>,
+    clase: Option<i32>,
+}
+
+// Nombre, atributos, etiquetas de clase y filas de un fichero leído
+type FilasLeidas = (String, Vec<String>, CodificadorEtiquetas, Vec<FilaLeida>);
+
+// Lee el conjunto de datos, en formato ARFF o CSV según la extensión
+// del fichero, tratando los valores ausentes según la política dada
+
+pub fn leer_datos(path: &str, politica: PoliticaAusentes)
+                  -> Result<(Dataset, ResumenCarga), Box<dyn Error>> {
+    let (nombre, atributos, clases, filas) =
+        if path.to_lowercase().ends_with(".arff") {
+            leer_filas_arff(path)?
+        } else {
+            leer_filas_csv(path)?
+        };
+
+    let (muestras, resumen) = tratar_ausentes(path, &atributos, filas,
+                                              politica)?;
+
+    Ok((Dataset { nombre, atributos, clases, muestras }, resumen))
+}
+
+// En los CSV la primera fila es la cabecera y la clase la última
+// columna. El número de atributos se toma de la cabecera y las
+// etiquetas de clase se codifican en orden de aparición
+
+fn leer_filas_csv(path: &str) -> Result<FilasLeidas, Box<dyn Error>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+
+    let cabecera = rdr.headers()?.clone();
+    if cabecera.len() < 2 {
+        return Err(format!("{}: se necesita al menos un atributo y la \
+                            clase", path).into());
+    }
+    let num_attributes = cabecera.len() - 1;
+    let atributos: Vec<String> = cabecera.iter()
+        .take(num_attributes)
+        .map(|nombre| nombre.trim_matches('\'').to_string())
+        .collect();
+
+    let mut clases = CodificadorEtiquetas::new();
+    let mut filas: Vec<FilaLeida> = Vec::new();
+    for result in rdr.records() {
+        let record = result?;
+        let linea = record.position().map_or(0, |p| p.line() as usize);
+
+        if record.len() != num_attributes + 1 {
+            return Err(format!("{}, línea {}: se esperaban {} columnas y se \
+                                encontraron {}", path, linea,
+                               num_attributes + 1, record.len()).into());
+        }
+
+        let mut valores = Vec::with_capacity(num_attributes);
+        for (columna, field) in record.iter().take(num_attributes)
+            .enumerate() {
+            if es_ausente(field) {
+                valores.push(None);
+            } else {
+                match field.parse::<f32>() {
+                    Ok(v) => valores.push(Some(v)),
+                    Err(_) => return Err(error_carga(
+                        path, linea, columna + 1,
+                        format!("valor '{}' no numérico para '{}'", field,
+                                atributos[columna]))),
+                }
+            }
+        }
+
+        let etiqueta = record[num_attributes].trim_matches('\'');
+        let clase = if es_ausente(etiqueta) {
+            None
+        } else {
+            Some(clases.codificar(etiqueta))
+        };
+
+        filas.push(FilaLeida { linea, valores, clase });
+    }
+
+    Ok((nombre_fichero(path), atributos, clases, filas))
+}
+
+// En los ARFF la clase es el último atributo. Si es nominal las
+// etiquetas se codifican en el orden en que las declara la cabecera
+
+fn leer_filas_arff(path: &str) -> Result<FilasLeidas, Box<dyn Error>> {
+    let Arff { relacion, atributos, instancias, lineas } =
+        arff::leer_arff(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    if atributos.len() < 2 {
+        return Err(format!("{}: se necesita al menos un atributo y la \
+                            clase", path).into());
+    }
+    let num_attributes = atributos.len() - 1;
+    for atributo in atributos[..num_attributes].iter() {
+        if atributo.tipo != TipoAtributo::Numerico {
+            return Err(format!("{}: el atributo '{}' no es numérico",
+                               path, atributo.nombre).into());
+        }
+    }
+    let valores_clase: &[String] = match atributos[num_attributes].tipo {
+        TipoAtributo::Nominal(ref valores) => valores,
+        _ => &[],
+    };
+    let mut clases = CodificadorEtiquetas::con_etiquetas(valores_clase);
+
+    let mut filas: Vec<FilaLeida> = Vec::with_capacity(instancias.len());
+    for (instancia, linea) in instancias.iter().zip(lineas) {
+        let valores: Vec<Option<f32>> = instancia[..num_attributes].iter()
+            .map(|valor| match *valor {
+                Valor::Numerico(v) if !v.is_nan() => Some(v),
+                _ => None,
+            })
+            .collect();
+
+        let clase = match instancia[num_attributes] {
+            Valor::Numerico(v) => Some(clases.codificar(&v.to_string())),
+            // El índice es la posición en la cabecera, que puede repetir
+            // valores que el codificador sólo cuenta una vez
+            Valor::Nominal(indice) => {
+                Some(clases.codificar(&valores_clase[indice]))
+            }
+            Valor::Cadena(ref etiqueta) => Some(clases.codificar(etiqueta)),
+            Valor::Ausente => None,
+        };
+
+        filas.push(FilaLeida { linea, valores, clase });
+    }
+
+    let atributos = atributos.into_iter()
+        .take(num_attributes)
+        .map(|atributo| atributo.nombre)
+        .collect();
+
+    Ok((relacion, atributos, clases, filas))
+}
+
+// Aplica la política de valores ausentes y construye las muestras.
+// La clase no se puede imputar: una fila sin clase se elimina con
+// EliminarFila y es un error con cualquier otra política. Los valores
+// que se imputarán quedan como NaN (ver Imputador)
+
+fn tratar_ausentes(
+    path: &str,
+    atributos: &[String],
+    filas: Vec<FilaLeida>,
+    politica: PoliticaAusentes)
+    -> Result<(Muestras, ResumenCarga), Box<dyn Error>> {
+
+    let num_attributes = atributos.len();
+    let mut resumen = ResumenCarga {
+        filas_leidas: filas.len(),
+        ..ResumenCarga::default()
+    };
+
+    for fila in filas.iter() {
+        resumen.celdas_ausentes +=
+            fila.valores.iter().filter(|v| v.is_none()).count();
+        if fila.clase.is_none() {
+            resumen.celdas_ausentes += 1;
+        }
+
+        let admite_ausentes = match politica {
+            PoliticaAusentes::Error => false,
+            PoliticaAusentes::EliminarFila => true,
+            _ => fila.clase.is_some(),
+        };
+        if !admite_ausentes {
+            // Sin clase la fila no se puede usar aunque se imputen sus
+            // atributos, así que se indica antes esa columna
+            let columna = if fila.clase.is_none() {
+                Some(num_attributes + 1)
+            } else {
+                fila.valores.iter().position(|v| v.is_none())
+                    .map(|columna| columna + 1)
+            };
+            if let Some(columna) = columna {
+                return Err(error_carga(path, fila.linea, columna,
+                                       "valor ausente".to_string()));
+            }
+        }
+    }
+
+    // Un atributo sin ningún valor conocido no se podrá imputar en
+    // ninguna partición
+    if politica == PoliticaAusentes::ImputarMedia ||
+        politica == PoliticaAusentes::ImputarMediana {
+        for (atributo, nombre) in atributos.iter().enumerate() {
+            if filas.iter().all(|fila| fila.valores[atributo].is_none()) {
+                return Err(format!("{}: el atributo '{}' no tiene ningún \
+                                    valor conocido", path, nombre).into());
+            }
+        }
+    }
+
+    let mut muestras = Muestras::new(num_attributes);
+    let mut attributes = Vec::with_capacity(num_attributes);
+    for fila in filas.into_iter() {
+        let clase = match fila.clase {
+            Some(clase) if fila.valores.iter().all(|v| v.is_some()) ||
+                politica != PoliticaAusentes::EliminarFila => clase,
+            _ => {
+                resumen.filas_eliminadas += 1;
+                continue;
+            }
+        };
+
+        attributes.clear();
+        for valor in fila.valores.iter() {
+            match *valor {
+                Some(v) => attributes.push(v),
+                None => {
+                    attributes.push(f32::NAN);
+                    resumen.celdas_imputadas += 1;
+                }
+            }
+        }
+
+        // Los identificadores son consecutivos entre las filas que se
+        // conservan
+        muestras.anadir(&attributes, clase);
+    }
+
+    if muestras.is_empty() {
+        return Err(format!("{}: no queda ninguna muestra tras la carga",
+                           path).into());
+    }
+
+    Ok((muestras, resumen))
+}
+
+// Valor con el que se sustituyen los ausentes (NaN) de cada atributo.
+// Se ajusta con las muestras de entrenamiento de cada partición y se
+// aplica a todas, como el escalado
+#[derive(Clone, Debug)]
+pub struct Imputador {
+    valores: Vec<f32>,
+}
+
+impl Imputador {
+    // Media o mediana de los valores conocidos de cada atributo en las
+    // muestras. Si algún atributo no tiene ninguno devuelve su índice
+    pub fn ajustar(politica: PoliticaAusentes, datos: Vista)
+                   -> Result<Imputador, usize> {
+        let mut valores = Vec::with_capacity(datos.num_atributos());
+        for atributo in 0..datos.num_atributos() {
+            let mut conocidos: Vec<f32> = datos.iter()
+                .map(|miembro| miembro.get_attribute(atributo))
+                .filter(|v| !v.is_nan())
+                .collect();
+            if conocidos.is_empty() {
+                return Err(atributo);
+            }
+            valores.push(if politica == PoliticaAusentes::ImputarMediana {
+                mediana(&mut conocidos)
+            } else {
+                media(&conocidos)
+            });
+        }
+
+        Ok(Imputador { valores })
+    }
+
+    // Sustituye los ausentes de todas las muestras
+    pub fn aplicar(&self, datos: &mut Muestras) {
+        for fila in datos.filas_mut() {
+            for (valor, imputado) in fila.iter_mut().zip(self.valores.iter()) {
+                if valor.is_nan() {
+                    *valor = *imputado;
+                }
+            }
+        }
+    }
+}
+
+// Celdas vacías, ?, NA y cualquier valor que se lea como NaN (nan,
+// -nan, +NaN...)
+
+fn es_ausente(campo: &str) -> bool {
+    campo.is_empty() || campo == "?" || campo.eq_ignore_ascii_case("na") ||
+        campo.parse::<f32>().is_ok_and(f32::is_nan)
+}
+
+fn media(valores: &[f32]) -> f32 {
+    valores.iter().sum::<f32>() / valores.len() as f32
+}
+
+fn mediana(valores: &mut [f32]) -> f32 {
+    valores.sort_by(f32::total_cmp);
+    let mitad = valores.len() / 2;
+    if valores.len().is_multiple_of(2) {
+        (valores[mitad - 1] + valores[mitad]) / 2.0
+    } else {
+        valores[mitad]
+    }
+}
+
+// Nombre del fichero sin directorio ni extensión
+
+pub fn nombre_fichero(path: &str) -> String {
+    let fichero = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    match fichero.rfind('.') {
+        Some(posicion) if posicion > 0 => fichero[..posicion].to_string(),
+        _ => fichero.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[test]
+    fn cualquier_nan_es_ausente() {
+        for campo in ["", "?", "NA", "na", "nan", "NaN", "-nan", "+nan",
+                      "+NaN"].iter() {
+            assert!(es_ausente(campo), "'{}' no es ausente", campo);
+        }
+        for campo in ["0", "-1.5", "inf", "nana", "n/a"].iter() {
+            assert!(!es_ausente(campo), "'{}' es ausente", campo);
+        }
+    }
+
+    // Una clase nominal declarada con un valor repetido tiene tantas
+    // clases como valores distintos
+    #[test]
+    fn clase_nominal_con_valores_repetidos() {
+        let path = env::temp_dir().join(
+            format!("mh_p1_nominal_repetido_{}.arff", process::id()));
+        fs::write(&path, "@relation r\n\
+                          @attribute x numeric\n\
+                          @attribute c {a, b, a, c}\n\
+                          @data\n\
+                          1, c\n\
+                          2, a\n\
+                          3, b\n").unwrap();
+        let leido = leer_datos(path.to_str().unwrap(),
+                               PoliticaAusentes::Error);
+        fs::remove_file(&path).unwrap();
+
+        let (dataset, _) = leido.unwrap();
+        assert_eq!(dataset.clases.etiquetas(), ["a", "b", "c"]);
+        let clases: Vec<i32> = (0..dataset.muestras.len())
+            .map(|fila| dataset.muestras.muestra(fila).get_class())
+            .collect();
+        assert_eq!(clases, vec![2, 0, 1]);
+    }
+
+    // Sin clase la fila es un error aunque se imputen los atributos, y
+    // se señala la columna de la clase
+    #[test]
+    fn fila_sin_clase_se_indica_en_su_columna() {
+        let path = env::temp_dir().join(
+            format!("mh_p1_sin_clase_{}.csv", process::id()));
+        fs::write(&path, "x,y,clase\n1,2,a\n?,3,?\n").unwrap();
+        let leido = leer_datos(path.to_str().unwrap(),
+                               PoliticaAusentes::ImputarMedia);
+        fs::remove_file(&path).unwrap();
+
+        let err = leido.err().unwrap();
+        let err = err.downcast_ref::<ErrorCarga>().unwrap();
+        assert_eq!((err.linea, err.columna), (3, 3));
+    }
+
+    // La imputación sólo usa las muestras con las que se ajusta, y
+    // sustituye los ausentes de todas
+    #[test]
+    fn imputacion_ajustada_en_entrenamiento() {
+        let mut muestras = Muestras::new(2);
+        for fila in [[1.0, f32::NAN], [3.0, 4.0], [f32::NAN, 8.0],
+                     [100.0, f32::NAN], [f32::NAN, 50.0]].iter() {
+            muestras.anadir(fila, 0);
+        }
+        let entrenamiento = [0, 1, 2];
+
+        let media = Imputador::ajustar(PoliticaAusentes::ImputarMedia,
+                                       muestras.vista(&entrenamiento))
+            .unwrap();
+        let mut imputadas = muestras.clone();
+        media.aplicar(&mut imputadas);
+        let filas: Vec<Vec<f32>> = (0..imputadas.len())
+            .map(|fila| imputadas.muestra(fila).get_attributes().to_vec())
+            .collect();
+        assert_eq!(filas, vec![vec![1.0, 6.0], vec![3.0, 4.0],
+                               vec![2.0, 8.0], vec![100.0, 6.0],
+                               vec![2.0, 50.0]]);
+
+        let mediana = Imputador::ajustar(PoliticaAusentes::ImputarMediana,
+                                         muestras.vista(&[0, 1, 3]))
+            .unwrap();
+        assert_eq!(mediana.valores, vec![3.0, 4.0]);
+
+        // Sin ningún valor conocido del segundo atributo
+        assert_eq!(Imputador::ajustar(PoliticaAusentes::ImputarMedia,
+                                      muestras.vista(&[0, 3])).unwrap_err(),
+                   1);
+    }
+
+    #[test]
+    fn mediana_con_infinitos() {
+        assert_eq!(mediana(&mut [3.0, f32::NEG_INFINITY, 1.0]), 1.0);
+        assert_eq!(mediana(&mut [4.0, 1.0, f32::INFINITY, 2.0]), 3.0);
+    }
+}
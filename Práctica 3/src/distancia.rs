@@ -0,0 +1,271 @@
+// Distancias entre muestras
+//
+// Además de la euclídea original se pueden usar la euclídea al
+// cuadrado (sin la raíz, que el 1-NN no necesita porque no cambia el
+// orden de los vecinos), Manhattan, Minkowski de orden p, Chebyshev y
+// la distancia coseno (1 - similitud coseno). Todas tienen en cuenta
+// los pesos de los atributos multiplicando cada atributo por su peso,
+// como en la distancia euclídea ponderada original.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::datos::Muestra;
+use crate::nucleos;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distancia {
+    Euclidea,
+    EuclideaCuadrado,
+    Manhattan,
+    Minkowski(f32),
+    Chebyshev,
+    Coseno,
+}
+
+impl Distancia {
+    // Distancia sin pesos (todos los atributos con peso 1)
+    pub fn entre(&self, elemento1: Muestra, elemento2: Muestra) -> f32 {
+        self.calcular(elemento1.get_attributes(), elemento2.get_attributes(),
+                      None)
+    }
+
+    // Distancia con los pesos indicados, uno por atributo
+    pub fn ponderada(&self, elemento1: Muestra, elemento2: Muestra,
+                     pesos: &[f32]) -> f32 {
+        self.calcular(elemento1.get_attributes(), elemento2.get_attributes(),
+                      Some(pesos))
+    }
+
+    // Las distancias euclídea, Manhattan y Minkowski son una función de
+    // la suma, para cada atributo, de un factor que sólo depende del
+    // peso por un término que sólo depende de la diferencia entre las
+    // muestras. Así los términos se pueden calcular una sola vez para
+    // cualquier vector de pesos
+    pub fn es_aditiva(&self) -> bool {
+        !matches!(self, Distancia::Chebyshev | Distancia::Coseno)
+    }
+
+    // Término de un atributo a partir de la diferencia entre muestras
+    pub fn termino(&self, diferencia: f32) -> f32 {
+        match *self {
+            Distancia::Manhattan => diferencia.abs(),
+            Distancia::Minkowski(p) => diferencia.abs().powf(p),
+            _ => diferencia * diferencia,
+        }
+    }
+
+    // Factor de un atributo a partir de su peso. Como el peso multiplica
+    // a la diferencia, es el mismo término aplicado al peso
+    pub fn factor(&self, peso: f32) -> f32 {
+        self.termino(peso)
+    }
+
+    // Distancia a partir de la suma de los términos por sus factores
+    pub fn desde_suma(&self, suma: f32) -> f32 {
+        match *self {
+            Distancia::Euclidea => suma.sqrt(),
+            Distancia::Minkowski(p) => suma.powf(1.0 / p),
+            _ => suma,
+        }
+    }
+
+    // Cota inferior de la distancia entre dos muestras cuyos atributos
+    // (ya ponderados) difieren en diferencia en uno de ellos. Se calcula
+    // con las mismas operaciones que la distancia, así que tampoco la
+    // supera por el redondeo. La distancia coseno no tiene cota
+    pub fn cota_atributo(&self, diferencia: f32) -> Option<f32> {
+        match *self {
+            Distancia::Chebyshev => Some(diferencia.abs()),
+            Distancia::Coseno => None,
+            _ => Some(self.desde_suma(self.termino(diferencia))),
+        }
+    }
+
+    // Distancia entre dos filas de atributos, con pesos o sin ellos. La
+    // euclídea y la de Manhattan usan los núcleos vectorizados
+    fn calcular(&self, atributos1: &[f32], atributos2: &[f32],
+                pesos: Option<&[f32]>) -> f32 {
+        let peso = |atributo: usize| pesos.map_or(1.0, |p| p[atributo]);
+        let diferencias = atributos1.iter().zip(atributos2.iter())
+            .enumerate()
+            .map(|(atributo, (a, b))| (a - b) * peso(atributo));
+
+        match *self {
+            Distancia::Euclidea => {
+                nucleos::euclidea_cuadrado(atributos1, atributos2, pesos)
+                    .sqrt()
+            }
+            Distancia::EuclideaCuadrado => {
+                nucleos::euclidea_cuadrado(atributos1, atributos2, pesos)
+            }
+            Distancia::Manhattan => {
+                nucleos::manhattan(atributos1, atributos2, pesos)
+            }
+            Distancia::Minkowski(p) => {
+                diferencias.map(|dif| dif.abs().powf(p))
+                    .sum::<f32>()
+                    .powf(1.0 / p)
+            }
+            Distancia::Chebyshev => {
+                diferencias.map(|dif| dif.abs()).fold(0.0, f32::max)
+            }
+            Distancia::Coseno => {
+                let (mut producto, mut norma1, mut norma2) = (0.0, 0.0, 0.0);
+                for (atributo, (a, b)) in atributos1.iter()
+                    .zip(atributos2.iter()).enumerate() {
+                    let p = peso(atributo);
+                    let (a, b) = (a * p, b * p);
+                    producto += a * b;
+                    norma1 += a * a;
+                    norma2 += b * b;
+                }
+                // Sin dirección definida se consideran ortogonales
+                if norma1 == 0.0 || norma2 == 0.0 {
+                    1.0
+                } else {
+                    1.0 - producto / (norma1.sqrt() * norma2.sqrt())
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Distancia {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<Distancia, String> {
+        let texto = texto.to_lowercase();
+        if let Some(p) = texto.strip_prefix("minkowski:") {
+            return match p.parse::<f32>() {
+                Ok(p) if p >= 1.0 && p.is_finite() => {
+                    Ok(Distancia::Minkowski(p))
+                }
+                _ => Err(format!("orden de Minkowski no válido '{}', debe \
+                                  ser al menos 1", p)),
+            };
+        }
+
+        match texto.as_str() {
+            "euclidea" => Ok(Distancia::Euclidea),
+            "euclidea2" => Ok(Distancia::EuclideaCuadrado),
+            "manhattan" => Ok(Distancia::Manhattan),
+            "chebyshev" => Ok(Distancia::Chebyshev),
+            "coseno" => Ok(Distancia::Coseno),
+            _ => Err(format!("distancia desconocida '{}', se admiten \
+                              euclidea, euclidea2, manhattan, minkowski:P, \
+                              chebyshev y coseno", texto)),
+        }
+    }
+}
+
+impl fmt::Display for Distancia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Distancia::Euclidea => write!(f, "euclidea"),
+            Distancia::EuclideaCuadrado => write!(f, "euclidea2"),
+            Distancia::Manhattan => write!(f, "manhattan"),
+            Distancia::Minkowski(p) => write!(f, "minkowski:{}", p),
+            Distancia::Chebyshev => write!(f, "chebyshev"),
+            Distancia::Coseno => write!(f, "coseno"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datos::Muestras;
+
+    const TODAS: [Distancia; 6] = [
+        Distancia::Euclidea, Distancia::EuclideaCuadrado,
+        Distancia::Manhattan, Distancia::Minkowski(3.0), Distancia::Chebyshev,
+        Distancia::Coseno,
+    ];
+
+    fn muestras(filas: &[&[f32]]) -> Muestras {
+        let mut muestras = Muestras::new(filas[0].len());
+        for fila in filas.iter() {
+            muestras.anadir(fila, 0);
+        }
+        muestras
+    }
+
+    fn aproximado(valor: f32, esperado: f32) {
+        assert!((valor - esperado).abs() < 1e-5,
+                "{} en lugar de {}", valor, esperado);
+    }
+
+    #[test]
+    fn distancias_sin_pesos() {
+        let m = muestras(&[&[1.0, 1.0, 0.5], &[4.0, -3.0, 0.5]]);
+        let (a, b) = (m.muestra(0), m.muestra(1));
+        aproximado(Distancia::Euclidea.entre(a, b), 5.0);
+        aproximado(Distancia::EuclideaCuadrado.entre(a, b), 25.0);
+        aproximado(Distancia::Manhattan.entre(a, b), 7.0);
+        aproximado(Distancia::Minkowski(3.0).entre(a, b), 91f32.cbrt());
+        aproximado(Distancia::Chebyshev.entre(a, b), 4.0);
+        for distancia in TODAS.iter() {
+            aproximado(distancia.entre(a, a), 0.0);
+            assert_eq!(distancia.entre(a, b), distancia.entre(b, a));
+        }
+
+        // Coseno: misma dirección, ortogonales y sin dirección
+        let m = muestras(&[&[1.0, 2.0], &[2.0, 4.0], &[-2.0, 1.0],
+                           &[0.0, 0.0]]);
+        aproximado(Distancia::Coseno.entre(m.muestra(0), m.muestra(1)), 0.0);
+        aproximado(Distancia::Coseno.entre(m.muestra(0), m.muestra(2)), 1.0);
+        assert_eq!(Distancia::Coseno.entre(m.muestra(0), m.muestra(3)), 1.0);
+    }
+
+    // Los pesos multiplican a cada atributo, y las aditivas se obtienen
+    // igual a partir de sus términos y factores
+    #[test]
+    fn distancias_con_pesos() {
+        let m = muestras(&[&[0.0, 0.0, 0.0, 1.0, 0.2],
+                           &[3.0, 4.0, 9.0, 0.0, 0.7]]);
+        let (a, b) = (m.muestra(0), m.muestra(1));
+        let pesos = [1.0, 0.5, 0.0, 0.3, 1.0];
+        aproximado(Distancia::Manhattan.ponderada(a, b, &pesos), 5.8);
+        aproximado(Distancia::Chebyshev.ponderada(a, b, &pesos), 3.0);
+        aproximado(Distancia::Euclidea.ponderada(a, b, &[1.0; 5]),
+                   Distancia::Euclidea.entre(a, b));
+
+        for distancia in TODAS.iter().filter(|d| d.es_aditiva()) {
+            let suma: f32 = a.get_attributes().iter()
+                .zip(b.get_attributes().iter())
+                .zip(pesos.iter())
+                .map(|((x, y), &peso)| {
+                    distancia.factor(peso) * distancia.termino(x - y)
+                })
+                .sum();
+            aproximado(distancia.desde_suma(suma),
+                       distancia.ponderada(a, b, &pesos));
+        }
+        assert!(!Distancia::Chebyshev.es_aditiva());
+        assert!(!Distancia::Coseno.es_aditiva());
+    }
+
+    #[test]
+    fn cota_de_un_atributo() {
+        let m = muestras(&[&[0.0, 0.0, 0.0], &[0.5, -2.0, 1.0]]);
+        let (a, b) = (m.muestra(0), m.muestra(1));
+        for distancia in TODAS.iter() {
+            match distancia.cota_atributo(-2.0) {
+                Some(cota) => assert!(cota <= distancia.entre(a, b)),
+                None => assert_eq!(*distancia, Distancia::Coseno),
+            }
+        }
+    }
+
+    #[test]
+    fn desde_texto() {
+        for distancia in TODAS.iter() {
+            assert_eq!(distancia.to_string().parse(), Ok(*distancia));
+        }
+        assert_eq!("Minkowski:1.5".parse(), Ok(Distancia::Minkowski(1.5)));
+        assert!("minkowski:0.5".parse::<Distancia>().is_err());
+        assert!("minkowski:inf".parse::<Distancia>().is_err());
+        assert!("hamming".parse::<Distancia>().is_err());
+    }
+}
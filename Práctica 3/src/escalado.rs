@@ -0,0 +1,224 @@
+// Escalado de los atributos
+//
+// El escalador se ajusta sobre un conjunto de muestras (normalmente el
+// de entrenamiento de cada partición) y después se aplica tal cual a
+// todas, de forma que los datos de validación no influyen en los
+// parámetros del escalado.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::datos::{Muestras, Vista};
+
+// Estrategias de escalado disponibles
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TipoEscalado {
+    // (valor - mínimo) / (máximo - mínimo), a [0, 1]
+    MinMax,
+    // (valor - media) / desviación típica
+    ZScore,
+    // (valor - mediana) / rango intercuartílico
+    Robusto,
+    // valor / máximo valor absoluto, a [-1, 1]
+    MaxAbs,
+    // Los datos se dejan tal cual
+    Ninguno,
+}
+
+impl FromStr for TipoEscalado {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<TipoEscalado, String> {
+        match texto {
+            "minmax" => Ok(TipoEscalado::MinMax),
+            "zscore" => Ok(TipoEscalado::ZScore),
+            "robusto" => Ok(TipoEscalado::Robusto),
+            "maxabs" => Ok(TipoEscalado::MaxAbs),
+            "ninguno" => Ok(TipoEscalado::Ninguno),
+            _ => Err(format!("escalado desconocido '{}' (minmax, zscore, \
+                              robusto, maxabs o ninguno)", texto)),
+        }
+    }
+}
+
+impl fmt::Display for TipoEscalado {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nombre = match self {
+            TipoEscalado::MinMax => "minmax",
+            TipoEscalado::ZScore => "zscore",
+            TipoEscalado::Robusto => "robusto",
+            TipoEscalado::MaxAbs => "maxabs",
+            TipoEscalado::Ninguno => "ninguno",
+        };
+        write!(f, "{}", nombre)
+    }
+}
+
+// Parámetros del escalado de cada atributo: valor' = (valor -
+// desplazamiento) / escala
+#[derive(Clone, Debug)]
+pub struct Escalador {
+    tipo: TipoEscalado,
+    desplazamiento: Vec<f32>,
+    escala: Vec<f32>,
+}
+
+impl Escalador {
+    // Ajusta el escalado indicado sobre las muestras. Si un atributo
+    // no tiene dispersión (escala 0) sólo se desplaza. Los ausentes que
+    // quedan por imputar (NaN) no cuentan, y al aplicarlo siguen
+    // siendo NaN
+    pub fn ajustar(tipo: TipoEscalado, datos: Vista) -> Escalador {
+        let num_attributes = datos.num_atributos();
+        let mut desplazamiento = Vec::with_capacity(num_attributes);
+        let mut escala = Vec::with_capacity(num_attributes);
+
+        for atributo in 0..num_attributes {
+            let mut valores: Vec<f32> = datos.iter()
+                .map(|miembro| miembro.get_attribute(atributo))
+                .filter(|v| !v.is_nan())
+                .collect();
+
+            let (centro, dispersion) = match tipo {
+                TipoEscalado::MinMax => {
+                    let minimo = valores.iter().cloned()
+                        .fold(f32::MAX, f32::min);
+                    let maximo = valores.iter().cloned()
+                        .fold(f32::MIN, f32::max);
+                    (minimo, maximo - minimo)
+                }
+                TipoEscalado::ZScore => {
+                    let n = valores.len() as f32;
+                    let media = valores.iter().sum::<f32>() / n;
+                    let varianza = valores.iter()
+                        .map(|v| (v - media) * (v - media))
+                        .sum::<f32>() / n;
+                    (media, varianza.sqrt())
+                }
+                TipoEscalado::Robusto => {
+                    valores.sort_by(f32::total_cmp);
+                    (percentil(&valores, 0.5),
+                     percentil(&valores, 0.75) - percentil(&valores, 0.25))
+                }
+                TipoEscalado::MaxAbs => {
+                    (0.0, valores.iter().fold(0.0, |m: f32, v| m.max(v.abs())))
+                }
+                TipoEscalado::Ninguno => (0.0, 1.0),
+            };
+
+            desplazamiento.push(centro);
+            escala.push(if dispersion != 0.0 { dispersion } else { 1.0 });
+        }
+
+        Escalador { tipo, desplazamiento, escala }
+    }
+
+    // Escala todas las muestras con los parámetros ya ajustados
+    pub fn aplicar(&self, datos: &mut Muestras) {
+        if self.tipo == TipoEscalado::Ninguno {
+            return;
+        }
+        for fila in datos.filas_mut() {
+            for ((valor, desplazamiento), escala) in fila.iter_mut()
+                .zip(self.desplazamiento.iter())
+                .zip(self.escala.iter()) {
+                *valor = (*valor - desplazamiento) / escala;
+            }
+        }
+    }
+}
+
+// Percentil p (entre 0 y 1) de unos valores ya ordenados, interpolando
+// linealmente entre las dos posiciones más cercanas
+
+fn percentil(ordenados: &[f32], p: f32) -> f32 {
+    let posicion = p * (ordenados.len() - 1) as f32;
+    let inferior = posicion.floor() as usize;
+    let superior = posicion.ceil() as usize;
+    let fraccion = posicion - inferior as f32;
+    ordenados[inferior] + (ordenados[superior] - ordenados[inferior]) * fraccion
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn muestras(filas: &[[f32; 2]]) -> Muestras {
+        let mut muestras = Muestras::new(2);
+        for fila in filas.iter() {
+            muestras.anadir(fila, 0);
+        }
+        muestras
+    }
+
+    fn filas(muestras: &Muestras) -> Vec<Vec<f32>> {
+        (0..muestras.len())
+            .map(|fila| muestras.muestra(fila).get_attributes().to_vec())
+            .collect()
+    }
+
+    // El escalado sólo se ajusta con las muestras indicadas, así que al
+    // aplicarlo a todas las de validación pueden quedar fuera de [0, 1]
+    #[test]
+    fn min_max_ajustado_en_entrenamiento() {
+        let mut datos = muestras(&[[2.0, 5.0], [4.0, 5.0], [3.0, 5.0],
+                                   [6.0, 1.0], [0.0, 5.0]]);
+        let entrenamiento = [0, 1, 2];
+        let escalador = Escalador::ajustar(TipoEscalado::MinMax,
+                                           datos.vista(&entrenamiento));
+        escalador.aplicar(&mut datos);
+
+        // El segundo atributo es constante en entrenamiento: escala 1
+        assert_eq!(filas(&datos), vec![vec![0.0, 0.0], vec![1.0, 0.0],
+                                       vec![0.5, 0.0], vec![2.0, -4.0],
+                                       vec![-1.0, 0.0]]);
+    }
+
+    // Cada escalado con sus parámetros calculados a mano. El segundo
+    // atributo es constante, así que sólo se desplaza
+    #[test]
+    fn cada_tipo_de_escalado() {
+        let originales = [[1.0, 7.0], [5.0, 7.0], [2.0, 7.0], [3.0, 7.0],
+                          [4.0, 7.0]];
+        let todas = [0, 1, 2, 3, 4];
+        let escalar = |tipo: TipoEscalado| {
+            let mut datos = muestras(&originales);
+            Escalador::ajustar(tipo, datos.vista(&todas)).aplicar(&mut datos);
+            filas(&datos).iter()
+                .map(|fila| (fila[0], fila[1]))
+                .collect::<Vec<(f32, f32)>>()
+        };
+
+        // Media 3 y desviación típica √2
+        let z = 2f32.sqrt();
+        assert_eq!(escalar(TipoEscalado::ZScore),
+                   vec![(-2.0 / z, 0.0), (2.0 / z, 0.0), (-1.0 / z, 0.0),
+                        (0.0, 0.0), (1.0 / z, 0.0)]);
+        // Mediana 3 y rango intercuartílico 4 - 2
+        assert_eq!(escalar(TipoEscalado::Robusto),
+                   vec![(-1.0, 0.0), (1.0, 0.0), (-0.5, 0.0), (0.0, 0.0),
+                        (0.5, 0.0)]);
+        // Sin desplazamiento, así que el constante queda a 1
+        assert_eq!(escalar(TipoEscalado::MaxAbs),
+                   vec![(0.2, 1.0), (1.0, 1.0), (0.4, 1.0), (0.6, 1.0),
+                        (0.8, 1.0)]);
+        assert_eq!(escalar(TipoEscalado::Ninguno),
+                   originales.iter().map(|f| (f[0], f[1]))
+                   .collect::<Vec<(f32, f32)>>());
+
+        let mut negativos = muestras(&[[-4.0, 0.0], [2.0, 0.0]]);
+        Escalador::ajustar(TipoEscalado::MaxAbs, negativos.vista(&[0, 1]))
+            .aplicar(&mut negativos);
+        assert_eq!(filas(&negativos), vec![vec![-1.0, 0.0], vec![0.5, 0.0]]);
+    }
+
+    #[test]
+    fn desde_texto() {
+        for tipo in [TipoEscalado::MinMax, TipoEscalado::ZScore,
+                     TipoEscalado::Robusto, TipoEscalado::MaxAbs,
+                     TipoEscalado::Ninguno] {
+            assert_eq!(tipo.to_string().parse(), Ok(tipo));
+        }
+        assert!("normal".parse::<TipoEscalado>().is_err());
+    }
+}
@@ -0,0 +1,505 @@
+// Comparación estadística no paramétrica de los algoritmos
+//
+// Se comparan los valores de la función objetivo obtenidos por cada
+// algoritmo en los mismos bloques (conjunto de datos, semilla,
+// repetición y partición):
+//
+// - Test de los rangos con signo de Wilcoxon para cada par de
+//   algoritmos. Distribución exacta hasta 25 diferencias no nulas y
+//   aproximación normal (con corrección de empates y de continuidad)
+//   a partir de ahí.
+// - Test de Friedman con los rangos medios de cada algoritmo (rango 1
+//   el mejor), con corrección de empates, y comparación de cada
+//   algoritmo con el de mejor rango, con los p-valores ajustados por
+//   Holm y por Finner.
+
+use std::collections::HashMap;
+
+use crate::resumen::Resumen;
+use crate::Algoritmo;
+
+const MAXIMO_N_WILCOXON_EXACTO: usize = 25;
+
+pub struct ResultadoWilcoxon {
+    // Suma de rangos de las diferencias positivas (gana el primero) y
+    // negativas (gana el segundo)
+    pub r_mas: f64,
+    pub r_menos: f64,
+    // Diferencias no nulas
+    pub n: usize,
+    // p-valor bilateral
+    pub p_valor: f64,
+}
+
+pub struct ResultadoFriedman {
+    pub rangos_medios: Vec<f64>,
+    pub estadistico: f64,
+    pub p_valor: f64,
+    // Índice del algoritmo de control (mejor rango medio)
+    pub control: usize,
+    // Para cada algoritmo distinto del control: índice, p-valor sin
+    // ajustar, ajustado por Holm y ajustado por Finner
+    pub comparaciones: Vec<(usize, f64, f64, f64)>,
+}
+
+// Test de Wilcoxon para dos muestras emparejadas
+
+pub fn wilcoxon(x: &[f64], y: &[f64]) -> ResultadoWilcoxon {
+    let diferencias: Vec<f64> = x.iter().zip(y.iter())
+        .map(|(a, b)| a - b)
+        .filter(|d| *d != 0.0)
+        .collect();
+    let n = diferencias.len();
+
+    if n == 0 {
+        return ResultadoWilcoxon { r_mas: 0.0, r_menos: 0.0, n, p_valor: 1.0 };
+    }
+
+    let absolutas: Vec<f64> = diferencias.iter().map(|d| d.abs()).collect();
+    let rangos = rangos_medios(&absolutas);
+
+    let mut r_mas = 0.0;
+    let mut r_menos = 0.0;
+    for (diferencia, rango) in diferencias.iter().zip(rangos.iter()) {
+        if *diferencia > 0.0 {
+            r_mas += rango;
+        } else {
+            r_menos += rango;
+        }
+    }
+    let t = r_mas.min(r_menos);
+
+    let p_valor = if n <= MAXIMO_N_WILCOXON_EXACTO {
+        // Distribución exacta de la suma de rangos positivos. Los
+        // rangos se duplican para que los empates (x.5) sean enteros
+        let dobles: Vec<usize> = rangos.iter()
+            .map(|r| (r * 2.0).round() as usize)
+            .collect();
+        let total: usize = dobles.iter().sum();
+        let mut formas = vec![0.0f64; total + 1];
+        formas[0] = 1.0;
+        for &rango in dobles.iter() {
+            for suma in (rango..=total).rev() {
+                formas[suma] += formas[suma - rango];
+            }
+        }
+        let limite = (t * 2.0).round() as usize;
+        let favorables: f64 = formas[..=limite].iter().sum();
+        (2.0 * favorables / 2f64.powi(n as i32)).min(1.0)
+    } else {
+        let n = n as f64;
+        let media = n * (n + 1.0) / 4.0;
+        let correccion_empates: f64 = grupos_empatados(&absolutas).iter()
+            .map(|&t| (t * t * t - t) as f64)
+            .sum::<f64>() / 48.0;
+        let varianza = n * (n + 1.0) * (2.0 * n + 1.0) / 24.0 -
+            correccion_empates;
+        let z = ((t - media).abs() - 0.5).max(0.0) / varianza.sqrt();
+        (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+    };
+
+    ResultadoWilcoxon { r_mas, r_menos, n, p_valor }
+}
+
+// Test de Friedman. Cada fila es un bloque con el valor de cada
+// algoritmo (mayor es mejor)
+
+pub fn friedman(bloques: &[Vec<f64>]) -> ResultadoFriedman {
+    let n = bloques.len() as f64;
+    let k = bloques[0].len();
+
+    let mut medios = vec![0.0; k];
+    // Suma de t³ - t de los grupos de t algoritmos empatados en cada
+    // bloque
+    let mut empates = 0.0;
+    for bloque in bloques.iter() {
+        // Se ordena de mayor a menor para que el mejor tenga rango 1
+        let negados: Vec<f64> = bloque.iter().map(|v| -v).collect();
+        for (total, rango) in medios.iter_mut()
+            .zip(rangos_medios(&negados)) {
+            *total += rango;
+        }
+        empates += grupos_empatados(&negados).iter()
+            .map(|&t| (t * t * t - t) as f64)
+            .sum::<f64>();
+    }
+    for rango in medios.iter_mut() {
+        *rango /= n;
+    }
+
+    let kf = k as f64;
+    let suma_cuadrados: f64 = medios.iter().map(|r| r * r).sum();
+    let correccion_empates = 1.0 - empates / (n * (kf * kf * kf - kf));
+    // Si todos los algoritmos empatan en todos los bloques no hay
+    // ninguna diferencia
+    let estadistico = if correccion_empates > 0.0 {
+        12.0 * n / (kf * (kf + 1.0)) *
+            (suma_cuadrados - kf * (kf + 1.0) * (kf + 1.0) / 4.0) /
+            correccion_empates
+    } else {
+        0.0
+    };
+    let p_valor = chi_cuadrado_cola(estadistico, kf - 1.0);
+
+    // Comparación con el control
+    let control = (0..k)
+        .min_by(|&a, &b| medios[a].total_cmp(&medios[b]))
+        .unwrap();
+    let error_tipico = (kf * (kf + 1.0) / (6.0 * n)).sqrt();
+
+    let mut comparaciones: Vec<(usize, f64)> = (0..k)
+        .filter(|&i| i != control)
+        .map(|i| {
+            let z = (medios[i] - medios[control]) / error_tipico;
+            (i, 2.0 * (1.0 - normal_cdf(z.abs())))
+        })
+        .collect();
+    comparaciones.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let m = comparaciones.len() as f64;
+    let mut holm_maximo: f64 = 0.0;
+    let mut finner_maximo: f64 = 0.0;
+    let comparaciones = comparaciones.iter().enumerate()
+        .map(|(j, &(i, p))| {
+            let j = j as f64 + 1.0;
+            holm_maximo = holm_maximo.max(((m - j + 1.0) * p).min(1.0));
+            finner_maximo = finner_maximo
+                .max((1.0 - (1.0 - p).powf(m / j)).min(1.0));
+            (i, p, holm_maximo, finner_maximo)
+        })
+        .collect();
+
+    ResultadoFriedman { rangos_medios: medios, estadistico, p_valor,
+                        control, comparaciones }
+}
+
+// Muestra la comparación de los algoritmos con los resultados de los
+// resúmenes indicados (uno o varios conjuntos de datos). Sólo se usan
+// los bloques en los que se han ejecutado todos los algoritmos
+
+pub fn mostrar_comparacion(titulo: &str, resumenes: &[&Resumen]) {
+    let mut algoritmos: Vec<Algoritmo> = Vec::new();
+    for resumen in resumenes.iter() {
+        for algoritmo in resumen.algoritmos() {
+            if !algoritmos.contains(&algoritmo) {
+                algoritmos.push(algoritmo);
+            }
+        }
+    }
+    if algoritmos.len() < 2 {
+        return;
+    }
+
+    // Bloques: valor de f_objetivo de cada algoritmo por (conjunto,
+    // semilla, repetición, partición), en orden de ejecución
+    let mut orden: Vec<(usize, u64, usize, usize)> = Vec::new();
+    let mut valores: HashMap<(usize, u64, usize, usize), Vec<Option<f64>>> =
+        HashMap::new();
+    for (d, resumen) in resumenes.iter().enumerate() {
+        for (a, &algoritmo) in algoritmos.iter().enumerate() {
+            for r in resumen.resultados_de(algoritmo) {
+                let clave = (d, r.semilla, r.repeticion, r.particion);
+                let fila = valores.entry(clave).or_insert_with(|| {
+                    orden.push(clave);
+                    vec![None; algoritmos.len()]
+                });
+                fila[a] = Some(r.f_objetivo as f64);
+            }
+        }
+    }
+    let bloques: Vec<Vec<f64>> = orden.iter()
+        .filter_map(|clave| valores[clave].iter().cloned().collect())
+        .collect();
+    if bloques.len() < 2 {
+        return;
+    }
+
+    println!("=========================================");
+    println!("Comparación estadística: {} ({} bloques)", titulo,
+             bloques.len());
+
+    let friedman = friedman(&bloques);
+    println!("-- Friedman: chi2 = {:.4}, gl = {}, p = {:.6}",
+             friedman.estadistico, algoritmos.len() - 1, friedman.p_valor);
+    println!("\tAlgoritmo\tRango medio");
+    let mut por_rango: Vec<usize> = (0..algoritmos.len()).collect();
+    por_rango.sort_by(|&a, &b| friedman.rangos_medios[a]
+                      .total_cmp(&friedman.rangos_medios[b]));
+    for &i in por_rango.iter() {
+        println!("\t{}\t{:.4}", algoritmos[i].nombre(),
+                 friedman.rangos_medios[i]);
+    }
+
+    println!("-- Post-hoc frente a {} (control)",
+             algoritmos[friedman.control].nombre());
+    println!("\tAlgoritmo\tp\tp_Holm\tp_Finner");
+    for &(i, p, holm, finner) in friedman.comparaciones.iter() {
+        println!("\t{}\t{:.6}\t{:.6}\t{:.6}", algoritmos[i].nombre(), p,
+                 holm, finner);
+    }
+
+    println!("-- Wilcoxon por pares");
+    println!("\tAlgoritmo 1\tAlgoritmo 2\tR+\tR-\tN\tp");
+    for i in 0..algoritmos.len() {
+        for j in i + 1..algoritmos.len() {
+            let x: Vec<f64> = bloques.iter().map(|b| b[i]).collect();
+            let y: Vec<f64> = bloques.iter().map(|b| b[j]).collect();
+            let w = wilcoxon(&x, &y);
+            println!("\t{}\t{}\t{}\t{}\t{}\t{:.6}", algoritmos[i].nombre(),
+                     algoritmos[j].nombre(), w.r_mas, w.r_menos, w.n,
+                     w.p_valor);
+        }
+    }
+    println!();
+}
+
+// Rangos (desde 1) de los valores en orden creciente, asignando a los
+// empates la media de sus rangos
+
+fn rangos_medios(valores: &[f64]) -> Vec<f64> {
+    let mut indices: Vec<usize> = (0..valores.len()).collect();
+    indices.sort_by(|&a, &b| valores[a].total_cmp(&valores[b]));
+
+    let mut rangos = vec![0.0; valores.len()];
+    let mut inicio = 0;
+    while inicio < indices.len() {
+        let mut fin = inicio;
+        while fin + 1 < indices.len() &&
+            valores[indices[fin + 1]] == valores[indices[inicio]] {
+            fin += 1;
+        }
+        let rango = (inicio + fin) as f64 / 2.0 + 1.0;
+        for &indice in indices[inicio..=fin].iter() {
+            rangos[indice] = rango;
+        }
+        inicio = fin + 1;
+    }
+    rangos
+}
+
+// Tamaños de los grupos de valores empatados
+
+fn grupos_empatados(valores: &[f64]) -> Vec<usize> {
+    let mut ordenados = valores.to_vec();
+    ordenados.sort_by(f64::total_cmp);
+    let mut grupos = Vec::new();
+    let mut tam = 1;
+    for i in 1..=ordenados.len() {
+        if i < ordenados.len() && ordenados[i] == ordenados[i - 1] {
+            tam += 1;
+        } else {
+            if tam > 1 {
+                grupos.push(tam);
+            }
+            tam = 1;
+        }
+    }
+    grupos
+}
+
+// Función de distribución de la normal estándar
+
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * erfc(-z / std::f64::consts::SQRT_2)
+}
+
+// Función de error complementaria (aproximación de Chebyshev, error
+// relativo menor que 1.2e-7)
+
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t * (-z * z - 1.26551223 + t * (1.00002368 + t * (0.37409196 +
+        t * (0.09678418 + t * (-0.18628806 + t * (0.27886807 +
+        t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 +
+        t * 0.17087277))))))))).exp();
+    if x >= 0.0 { r } else { 2.0 - r }
+}
+
+// P(X > x) para una chi-cuadrado con gl grados de libertad
+
+fn chi_cuadrado_cola(x: f64, gl: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    gamma_q(gl / 2.0, x / 2.0)
+}
+
+// Función gamma incompleta regularizada superior Q(a, x), por serie
+// si x < a + 1 y por fracción continua en otro caso
+
+fn gamma_q(a: f64, x: f64) -> f64 {
+    const ITERACIONES: usize = 500;
+    const EPSILON: f64 = 1e-14;
+    let ln_prefijo = -x + a * x.ln() - ln_gamma(a);
+
+    if x < a + 1.0 {
+        let mut termino = 1.0 / a;
+        let mut suma = termino;
+        let mut denominador = a;
+        for _ in 0..ITERACIONES {
+            denominador += 1.0;
+            termino *= x / denominador;
+            suma += termino;
+            if termino.abs() < suma.abs() * EPSILON {
+                break;
+            }
+        }
+        1.0 - suma * ln_prefijo.exp()
+    } else {
+        // Algoritmo de Lentz
+        let minimo = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / minimo;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..ITERACIONES {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < minimo {
+                d = minimo;
+            }
+            c = b + an / c;
+            if c.abs() < minimo {
+                c = minimo;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        ln_prefijo.exp() * h
+    }
+}
+
+// Logaritmo de la función gamma (aproximación de Lanczos)
+
+fn ln_gamma(x: f64) -> f64 {
+    const COEFICIENTES: [f64; 6] = [76.18009172947146, -86.50532032941677,
+                                    24.01409824083091, -1.231739572450155,
+                                    0.1208650973866179e-2,
+                                    -0.5395239384953e-5];
+    let mut y = x;
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut serie = 1.000000000190015;
+    for coeficiente in COEFICIENTES.iter() {
+        y += 1.0;
+        serie += coeficiente / y;
+    }
+    -tmp + (2.5066282746310005 * serie / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parecidos(valor: f64, esperado: f64, tolerancia: f64) -> bool {
+        (valor - esperado).abs() <= tolerancia * esperado.abs().max(1e-300)
+    }
+
+    #[test]
+    fn erfc_valores_conocidos() {
+        // Con el error relativo de la aproximación
+        for &(x, esperado) in [(0.0, 1.0), (1.0, 0.15729920705028513),
+                               (2.0, 0.004677734981047265),
+                               (-1.0, 1.842700792949715)].iter() {
+            assert!(parecidos(erfc(x), esperado, 1.2e-7),
+                    "erfc({}) = {}", x, erfc(x));
+        }
+        assert!(parecidos(normal_cdf(1.959963984540054), 0.975, 1e-7));
+    }
+
+    #[test]
+    fn ln_gamma_valores_conocidos() {
+        assert!(ln_gamma(1.0).abs() < 1e-10);
+        assert!(parecidos(ln_gamma(0.5), 0.5723649429247004, 1e-10));
+        assert!(parecidos(ln_gamma(10.0), 12.801827480081467, 1e-10));
+    }
+
+    #[test]
+    fn chi_cuadrado_valores_criticos() {
+        // Valores críticos al 5% para 1, 2, 3 y 10 grados de libertad,
+        // que cubren la serie y la fracción continua
+        for &(x, gl) in [(3.841458820694124, 1.0), (5.991464547107979, 2.0),
+                         (7.814727903251178, 3.0),
+                         (18.307038053275146, 10.0)].iter() {
+            let p = chi_cuadrado_cola(x, gl);
+            assert!(parecidos(p, 0.05, 1e-9), "gl = {}: p = {}", gl, p);
+        }
+        // Con a = 1 es la exponencial
+        assert!(parecidos(gamma_q(1.0, 0.3), (-0.3f64).exp(), 1e-12));
+        assert_eq!(chi_cuadrado_cola(0.0, 3.0), 1.0);
+    }
+
+    #[test]
+    fn wilcoxon_exacto() {
+        // Diez diferencias positivas: p = 2 / 2^10
+        let x: Vec<f64> = (1..=10).map(|i| i as f64 * 2.0).collect();
+        let y: Vec<f64> = (1..=10).map(|i| i as f64).collect();
+        let w = wilcoxon(&x, &y);
+        assert_eq!((w.r_mas, w.r_menos, w.n), (55.0, 0.0, 10));
+        assert!(parecidos(w.p_valor, 0.001953125, 1e-12));
+
+        // Diferencias 1..5 y -6: T = 6 y 14 de los 64 subconjuntos de
+        // rangos suman 6 o menos
+        let x = [1.0, 2.0, 3.0, 4.0, 5.0, 0.0];
+        let y = [0.0, 0.0, 0.0, 0.0, 0.0, 6.0];
+        let w = wilcoxon(&x, &y);
+        assert_eq!((w.r_mas, w.r_menos, w.n), (15.0, 6.0, 6));
+        assert!(parecidos(w.p_valor, 0.4375, 1e-12));
+
+        // Sin diferencias
+        assert_eq!(wilcoxon(&[1.0, 2.0], &[1.0, 2.0]).p_valor, 1.0);
+    }
+
+    #[test]
+    fn friedman_sin_empates() {
+        // El primer algoritmo siempre gana y el tercero siempre pierde
+        let bloques = vec![vec![3.0, 2.0, 1.0]; 4];
+        let f = friedman(&bloques);
+        assert_eq!(f.rangos_medios, vec![1.0, 2.0, 3.0]);
+        assert!(parecidos(f.estadistico, 8.0, 1e-12));
+        assert!(parecidos(f.p_valor, 0.01831563888873418, 1e-9));
+        assert_eq!(f.control, 0);
+
+        // z = 2·√2 para el tercero y √2 para el segundo
+        let (i, p, holm, finner) = f.comparaciones[0];
+        assert_eq!(i, 2);
+        assert!(parecidos(p, 0.004677734981047265, 1e-6));
+        assert!(parecidos(holm, 2.0 * 0.004677734981047265, 1e-6));
+        assert!(parecidos(finner, 0.009333588757541644, 1e-6));
+        let (i, p, holm, finner) = f.comparaciones[1];
+        assert_eq!(i, 1);
+        assert!(parecidos(p, 0.15729920705028513, 1e-6));
+        assert!(parecidos(holm, p, 1e-12));
+        assert!(parecidos(finner, p, 1e-12));
+    }
+
+    #[test]
+    fn friedman_con_empates() {
+        // Rangos 1, 2, 3 y 1.5, 1.5, 3: sin corregir 3.25, y con la
+        // corrección 1 - 6 / (2·(27 - 3)) = 0.875
+        let bloques = vec![vec![3.0, 2.0, 1.0], vec![1.0, 1.0, 0.0]];
+        let f = friedman(&bloques);
+        assert_eq!(f.rangos_medios, vec![1.25, 1.75, 3.0]);
+        assert!(parecidos(f.estadistico, 3.25 / 0.875, 1e-12));
+        assert!(parecidos(f.p_valor, 0.15611804531597107, 1e-9));
+
+        // Todo empatado
+        let f = friedman(&[vec![1.0, 1.0, 1.0], vec![2.0, 2.0, 2.0]]);
+        assert_eq!(f.estadistico, 0.0);
+        assert_eq!(f.p_valor, 1.0);
+    }
+    // Un valor no numérico (por ejemplo la función objetivo de una
+    // partición vacía) no detiene la comparación: queda el último
+    #[test]
+    fn rangos_con_nan() {
+        assert_eq!(rangos_medios(&[2.0, f64::NAN, 1.0, 2.0]),
+                   vec![2.5, 4.0, 1.0, 2.5]);
+        assert_eq!(grupos_empatados(&[f64::NAN, 1.0, 1.0]), vec![2]);
+    }
+}
@@ -0,0 +1,87 @@
+// Codificación de las etiquetas de clase
+//
+// Internamente las clases se manejan como enteros consecutivos desde
+// 0. El codificador guarda la etiqueta original de cada una para
+// poder mostrarla en los resultados.
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default)]
+pub struct CodificadorEtiquetas {
+    etiquetas: Vec<String>,
+    indices: HashMap<String, i32>,
+}
+
+impl CodificadorEtiquetas {
+    pub fn new() -> CodificadorEtiquetas {
+        CodificadorEtiquetas::default()
+    }
+
+    // Codificador con las etiquetas ya conocidas (por ejemplo, los
+    // valores de un atributo nominal de un ARFF), respetando su orden
+    pub fn con_etiquetas(etiquetas: &[String]) -> CodificadorEtiquetas {
+        let mut codificador = CodificadorEtiquetas::new();
+        for etiqueta in etiquetas.iter() {
+            codificador.codificar(etiqueta);
+        }
+        codificador
+    }
+
+    // Devuelve el identificador de la etiqueta, asignándole uno nuevo
+    // si no se había visto antes
+    pub fn codificar(&mut self, etiqueta: &str) -> i32 {
+        if let Some(clase) = self.indices.get(etiqueta) {
+            return *clase;
+        }
+        let clase = self.etiquetas.len() as i32;
+        self.etiquetas.push(etiqueta.to_string());
+        self.indices.insert(etiqueta.to_string(), clase);
+        clase
+    }
+
+    pub fn decodificar(&self, clase: i32) -> &str {
+        &self.etiquetas[clase as usize]
+    }
+
+    pub fn num_clases(&self) -> usize {
+        self.etiquetas.len()
+    }
+
+    pub fn etiquetas(&self) -> &[String] {
+        &self.etiquetas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codifica_en_orden_de_aparicion() {
+        let mut codificador = CodificadorEtiquetas::new();
+        let clases: Vec<i32> = ["b", "Iris setosa", "b", "", "Iris setosa"]
+            .iter()
+            .map(|etiqueta| codificador.codificar(etiqueta))
+            .collect();
+
+        assert_eq!(clases, vec![0, 1, 0, 2, 1]);
+        assert_eq!(codificador.num_clases(), 3);
+        assert_eq!(codificador.etiquetas(), ["b", "Iris setosa", ""]);
+        assert_eq!(codificador.decodificar(1), "Iris setosa");
+        assert_eq!(codificador.decodificar(2), "");
+    }
+
+    #[test]
+    fn respeta_las_etiquetas_conocidas() {
+        let etiquetas = vec!["g".to_string(), "b".to_string(),
+                             "g".to_string()];
+        let mut codificador = CodificadorEtiquetas::con_etiquetas(&etiquetas);
+
+        assert_eq!(codificador.num_clases(), 2);
+        assert_eq!(codificador.codificar("b"), 1);
+        assert_eq!(codificador.codificar("g"), 0);
+        // Una etiqueta nueva va después de las conocidas
+        assert_eq!(codificador.codificar("x"), 2);
+        assert_eq!(codificador.decodificar(2), "x");
+    }
+}
@@ -0,0 +1,676 @@
+// Evaluación de la función objetivo durante el entrenamiento
+//
+// Los algoritmos no llaman directamente al clasificador, sino a un
+// evaluador asociado al conjunto de entrenamiento, a la función
+// objetivo y al clasificador elegidos. Así se lleva la cuenta de las
+// evaluaciones realmente realizadas en cada ejecución.
+//
+// El conjunto de entrenamiento no cambia durante una ejecución, así que
+// con las distancias aditivas (euclídea, Manhattan, Minkowski) el
+// evaluador calcula una sola vez el término de cada atributo para cada
+// par de muestras. La distancia de cada par es entonces el producto
+// escalar de sus términos con los factores de los pesos.
+//
+// Los algoritmos de trayectoria cambian un único peso en cada vecino.
+// Para ellos hay una evaluación incremental que guarda la suma de cada
+// par para la solución actual y la actualiza sólo con el término del
+// peso que cambia, en O(n²) en lugar de O(n²·d).
+//
+// Las evaluaciones de una población son independientes entre sí, así
+// que se reparten entre varios hilos. Una evaluación suelta (como las
+// de la evolución diferencial, que dependen de la anterior) reparte a
+// su vez los pares de muestras entre los hilos si son suficientes. En
+// ambos casos cada valor se calcula con las mismas operaciones y en el
+// mismo orden que con un solo hilo, así que los resultados no dependen
+// del número de hilos.
+//
+// Los términos ocupan memoria cuadrática en el número de muestras, y
+// también la suma de cada par que guarda cada evaluación en curso.
+// Los términos sólo dependen del conjunto de entrenamiento, así que se
+// calculan una vez por partición y los comparten todos sus algoritmos.
+// Si con las sumas de un trabajo de un solo hilo no caben en
+// MEMORIA_MAXIMA_DIFERENCIAS (o la distancia no es aditiva) no se
+// calculan, y cada evaluación clasifica directamente con el k-NN. La
+// decisión sólo depende del conjunto y de la distancia: las sumas de
+// los pares no redondean igual que la distancia directa, y los
+// resultados no deben cambiar con el número de hilos o de trabajos.
+// Son éstos los que se limitan para que quepan los de todos los
+// trabajos que se ejecutan a la vez. Las distancias de cada muestra
+// se obtienen de las sumas fila a fila, sin guardar la matriz
+// completa.
+//
+// El índice de árbol k-d sólo se usa entonces: con los términos, cada
+// evaluación (y más aún la incremental) es más rápida que buscar en el
+// árbol. Sin ellos, el árbol se construye una vez y cada evaluación
+// busca en él los vecinos.
+
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::datos::Vista;
+use crate::indice::ArbolKd;
+use crate::knn::Knn;
+use crate::objetivo::Objetivo;
+use crate::{evaluar_predicciones, peso_reducido, reducir_pesos,
+            tasa_reduccion};
+
+// Términos mínimos que debe sumar cada hilo al repartir los pares de
+// una evaluación, para que compense crearlo
+const TERMINOS_MINIMOS_POR_HILO: usize = 1 << 18;
+
+// Memoria máxima (en bytes) de los términos de los pares y de las
+// sumas de las evaluaciones en curso, entre todos los trabajos que se
+// ejecutan a la vez. Con un solo trabajo de un hilo y 40 atributos
+// caben unas 3600 muestras de entrenamiento
+pub const MEMORIA_MAXIMA_DIFERENCIAS: usize = 1 << 30;
+
+// Términos de la distancia de cada par de muestras (i, j), con i < j,
+// en un único vector contiguo con los de cada atributo seguidos. Los
+// pares van en orden: (0, 1), (0, 2), ..., (1, 2), ...
+pub struct Diferencias {
+    num_pares: usize,
+    terminos: Vec<f32>,
+}
+
+impl Diferencias {
+    // Memoria (en bytes) que ocupa un trabajo con los hilos indicados
+    // si se calculan los términos de un conjunto de n muestras, o None
+    // si no se calculan. Cada trabajo puede tener a la vez tantas
+    // evaluaciones como hilos más una incremental
+    pub fn memoria(n: usize, num_atributos: usize, knn: &Knn,
+                   hilos: usize) -> Option<usize> {
+        if !knn.distancia.es_aditiva() {
+            return None;
+        }
+
+        let num_pares = n * n.saturating_sub(1) / 2;
+        let bytes = |hilos: usize| {
+            // La incremental guarda las sumas actuales y las del
+            // candidato
+            let sumas = num_pares.checked_add(n)
+                .and_then(|memoria| memoria.checked_mul(hilos.max(1) + 2));
+            num_pares.checked_mul(num_atributos)
+                .and_then(|terminos| terminos.checked_add(sumas?))
+                .and_then(|floats| floats.checked_mul(mem::size_of::<f32>()))
+        };
+        match bytes(1) {
+            Some(minimo) if minimo <= MEMORIA_MAXIMA_DIFERENCIAS => {
+                Some(bytes(hilos).unwrap_or(usize::MAX))
+            }
+            _ => None,
+        }
+    }
+
+    // Términos de los pares del conjunto de entrenamiento con la
+    // distancia del clasificador, si es aditiva y caben en memoria
+    // (Diferencias::memoria)
+    pub fn calcular(datos: Vista, knn: &Knn) -> Option<Diferencias> {
+        let n = datos.len();
+        Diferencias::memoria(n, datos.num_atributos(), knn, 1)?;
+        Some(calcular_diferencias(datos, knn, n * n.saturating_sub(1) / 2))
+    }
+
+    fn atributo(&self, atributo: usize) -> &[f32] {
+        &self.terminos[atributo * self.num_pares..
+                       (atributo + 1) * self.num_pares]
+    }
+}
+
+// Memoria de trabajo de una evaluación con los términos de los pares,
+// que se reutiliza de una evaluación a otra en lugar de reservarla
+// cada vez
+#[derive(Default)]
+struct Memoria {
+    sumas: Vec<f32>,
+    fila: Vec<f32>,
+}
+
+pub struct Evaluador<'a> {
+    datos: Vista<'a>,
+    objetivo: &'a Objetivo,
+    knn: &'a Knn,
+    num_clases: usize,
+    diferencias: Option<Arc<Diferencias>>,
+    arbol: Option<ArbolKd<'a>>,
+    hilos: usize,
+    evaluaciones: AtomicUsize,
+    // Una por cada evaluación que puede estar en curso a la vez
+    memorias: Mutex<Vec<Memoria>>,
+}
+
+impl<'a> Evaluador<'a> {
+    // Las diferencias deben ser las del mismo conjunto de entrenamiento
+    // (Diferencias::calcular). Sin ellas se clasifica directamente, con
+    // el árbol k-d si se ha elegido
+    pub fn new(datos: Vista<'a>, objetivo: &'a Objetivo, knn: &'a Knn,
+               num_clases: usize, hilos: usize,
+               diferencias: Option<Arc<Diferencias>>) -> Evaluador<'a> {
+        let arbol = match diferencias {
+            Some(_) => None,
+            None => knn.arbol(datos),
+        };
+
+        Evaluador { datos, objetivo, knn, num_clases, diferencias, arbol,
+                    hilos: hilos.max(1), evaluaciones: AtomicUsize::new(0),
+                    memorias: Mutex::new(Vec::new()) }
+    }
+
+    pub fn num_atributos(&self) -> usize {
+        self.datos.num_atributos()
+    }
+
+    // Evalúa los pesos sobre el conjunto de entrenamiento (dejando uno
+    // fuera). Devuelve tasa de clasificación, de reducción y función
+    // objetivo
+    pub fn evaluar(&self, pesos: &[f32]) -> (f32, f32, f32) {
+        self.evaluar_con(pesos, self.hilos)
+    }
+
+    // Evalúa cada cromosoma de la población y devuelve su función
+    // objetivo, en el mismo orden. Los cromosomas se reparten en
+    // bloques consecutivos entre los hilos
+    pub fn evaluar_poblacion(&self, poblacion: &[Vec<f32>]) -> Vec<f32> {
+        let mut valores = vec![0.0; poblacion.len()];
+        let hilos = self.hilos.min(poblacion.len());
+        if hilos <= 1 {
+            for (valor, cromosoma) in valores.iter_mut().zip(poblacion) {
+                *valor = self.evaluar_con(cromosoma, 1).2;
+            }
+            return valores;
+        }
+
+        let tam_bloque = poblacion.len().div_ceil(hilos);
+        thread::scope(|s| {
+            for (bloque, valores) in poblacion.chunks(tam_bloque)
+                .zip(valores.chunks_mut(tam_bloque)) {
+                s.spawn(move || {
+                    for (valor, cromosoma) in valores.iter_mut().zip(bloque) {
+                        *valor = self.evaluar_con(cromosoma, 1).2;
+                    }
+                });
+            }
+        });
+        valores
+    }
+
+    // Evalúa los pesos repartiendo los pares entre, como mucho, los
+    // hilos indicados
+    fn evaluar_con(&self, pesos: &[f32], hilos: usize) -> (f32, f32, f32) {
+        self.evaluaciones.fetch_add(1, Ordering::Relaxed);
+
+        let (pesos_red, n_reducidos) = reducir_pesos(pesos);
+        let tasa_red = tasa_reduccion(n_reducidos, pesos_red.len() as f32);
+        match self.diferencias {
+            Some(ref diferencias) => {
+                let mut memoria = self.memorias.lock().unwrap()
+                    .pop().unwrap_or_default();
+                self.sumas(diferencias, &pesos_red, hilos,
+                           &mut memoria.sumas);
+                let valor = self.evaluar_sumas(&memoria.sumas, tasa_red,
+                                               &mut memoria.fila);
+                self.memorias.lock().unwrap().push(memoria);
+                valor
+            }
+            None => {
+                let v_clasificaciones = match self.arbol {
+                    Some(ref arbol) => self.knn.predecir_con_arbol(
+                        arbol, self.datos, Some(&pesos_red)),
+                    None => self.knn.predecir(
+                        self.datos, self.datos, Some(&pesos_red)),
+                };
+                evaluar_predicciones(self.datos, &v_clasificaciones,
+                                     tasa_red, self.objetivo,
+                                     self.num_clases).0
+            }
+        }
+    }
+
+    // Evalúa los pesos y prepara la evaluación incremental de los
+    // vecinos que cambian un solo peso
+    pub fn incremental(&self, pesos: &[f32])
+                       -> EvaluacionIncremental<'_, 'a> {
+        let mut sumas = Vec::new();
+        let mut fila = Vec::new();
+        let valor = match self.diferencias {
+            Some(ref diferencias) => {
+                self.evaluaciones.fetch_add(1, Ordering::Relaxed);
+                let (pesos_red, n_reducidos) = reducir_pesos(pesos);
+                let tasa_red = tasa_reduccion(n_reducidos,
+                                              pesos_red.len() as f32);
+                self.sumas(diferencias, &pesos_red, self.hilos, &mut sumas);
+                self.evaluar_sumas(&sumas, tasa_red, &mut fila)
+            }
+            None => self.evaluar(pesos),
+        };
+
+        EvaluacionIncremental {
+            evaluador: self,
+            pesos: pesos.to_vec(),
+            valor,
+            sumas,
+            candidato: None,
+            sumas_candidato: Vec::new(),
+            fila,
+            aceptados: 0,
+        }
+    }
+
+    // Número de evaluaciones realizadas hasta el momento
+    pub fn evaluaciones(&self) -> usize {
+        self.evaluaciones.load(Ordering::Relaxed)
+    }
+
+    // Deja en sumas la de cada par con los pesos (ya reducidos)
+    // indicados. Los atributos con peso cero no aportan nada. Los pares
+    // se reparten en bloques consecutivos entre los hilos
+    fn sumas(&self, diferencias: &Diferencias, pesos_red: &[f32],
+             hilos: usize, sumas: &mut Vec<f32>) {
+        let factores: Vec<(usize, f32)> = pesos_red.iter()
+            .map(|&peso| self.knn.distancia.factor(peso))
+            .enumerate()
+            .filter(|&(_, factor)| factor != 0.0)
+            .collect();
+        sumas.clear();
+        sumas.resize(diferencias.num_pares, 0.0);
+
+        let terminos = diferencias.num_pares * factores.len();
+        let hilos = hilos.min(terminos / TERMINOS_MINIMOS_POR_HILO);
+        if hilos <= 1 {
+            sumar_pares(sumas, 0, diferencias, &factores);
+            return;
+        }
+
+        let tam_bloque = diferencias.num_pares.div_ceil(hilos);
+        thread::scope(|s| {
+            for (i, bloque) in sumas.chunks_mut(tam_bloque).enumerate() {
+                let factores = &factores;
+                s.spawn(move || sumar_pares(bloque, i * tam_bloque,
+                                            diferencias, factores));
+            }
+        });
+    }
+
+    // Tasas y función objetivo a partir de la suma de cada par. El
+    // vector indicado se usa para las distancias de cada fila
+    fn evaluar_sumas(&self, sumas: &[f32], tasa_red: f32,
+                     fila: &mut Vec<f32>) -> (f32, f32, f32) {
+        let v_clasificaciones = self.predecir(sumas, fila);
+        evaluar_predicciones(self.datos, &v_clasificaciones, tasa_red,
+                             self.objetivo, self.num_clases).0
+    }
+
+    // Clasifica cada muestra dejándola fuera, a partir de la suma de
+    // cada par
+    fn predecir(&self, sumas: &[f32], fila: &mut Vec<f32>) -> Vec<i32> {
+        let n = self.datos.len();
+        fila.resize(n, 0.0);
+
+        (0..n)
+            .map(|i| {
+                self.distancias_fila(sumas, i, fila);
+                if self.knn.k == 1 {
+                    // Como el 1-NN original, en empate gana el primero
+                    let mut mas_cercano: Option<(f32, usize)> = None;
+                    for (j, &d) in fila.iter().enumerate() {
+                        if j != i
+                            && mas_cercano.is_none_or(|(m, _)| d < m) {
+                            mas_cercano = Some((d, j));
+                        }
+                    }
+                    mas_cercano.map_or(0, |(_, j)| {
+                        self.datos.muestra(j).get_class()
+                    })
+                } else {
+                    let candidatos = fila.iter().enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .map(|(j, &d)| {
+                            (d, self.datos.muestra(j).get_class())
+                        });
+                    self.knn.votar(&self.knn.mas_cercanos(candidatos))
+                }
+            })
+            .collect()
+    }
+
+    // Deja en la fila las distancias de la muestra i a todas, con cero
+    // para ella misma
+    fn distancias_fila(&self, sumas: &[f32], i: usize, fila: &mut [f32]) {
+        let distancia = self.knn.distancia;
+        let n = fila.len();
+        // Los pares (j, ·) empiezan en la posición j·(2n - j - 1)/2
+        let inicio = |j: usize| j * (2 * n - j - 1) / 2;
+
+        for (j, d) in fila[..i].iter_mut().enumerate() {
+            *d = distancia.desde_suma(sumas[inicio(j) + i - j - 1]);
+        }
+        fila[i] = 0.0;
+        let pares = &sumas[inicio(i)..inicio(i) + n - i - 1];
+        for (d, &suma) in fila[i + 1..].iter_mut().zip(pares.iter()) {
+            *d = distancia.desde_suma(suma);
+        }
+    }
+}
+
+// Evaluación incremental de una solución: se evalúan vecinos que
+// cambian un peso con evaluar_cambio y, si se acepta el último
+// evaluado, se pasa a él con aceptar_cambio. Los cambios rechazados no
+// modifican nada
+pub struct EvaluacionIncremental<'e, 'a> {
+    evaluador: &'e Evaluador<'a>,
+    pesos: Vec<f32>,
+    valor: (f32, f32, f32),
+    // Suma de cada par con los pesos actuales (vacío si no se usan los
+    // términos de los pares)
+    sumas: Vec<f32>,
+    // Último cambio evaluado, con la suma de cada par en
+    // sumas_candidato
+    candidato: Option<Cambio>,
+    sumas_candidato: Vec<f32>,
+    // Distancias de cada fila en la última evaluación
+    fila: Vec<f32>,
+    // Cambios aceptados desde que se calcularon las sumas desde cero
+    aceptados: usize,
+}
+
+struct Cambio {
+    atributo: usize,
+    peso: f32,
+    valor: (f32, f32, f32),
+}
+
+impl<'e, 'a> EvaluacionIncremental<'e, 'a> {
+    pub fn pesos(&self) -> &[f32] {
+        &self.pesos
+    }
+
+    // Tasas y función objetivo de la solución actual
+    pub fn valor(&self) -> (f32, f32, f32) {
+        self.valor
+    }
+
+    // Evalúa la solución actual con el peso del atributo cambiado
+    pub fn evaluar_cambio(&mut self, atributo: usize, peso: f32)
+                          -> (f32, f32, f32) {
+        let evaluador = self.evaluador;
+        let mut pesos = self.pesos.clone();
+        pesos[atributo] = peso;
+
+        let diferencias = match evaluador.diferencias {
+            Some(ref diferencias) => diferencias,
+            None => {
+                let valor = evaluador.evaluar(&pesos);
+                self.candidato = Some(Cambio { atributo, peso, valor });
+                return valor;
+            }
+        };
+        evaluador.evaluaciones.fetch_add(1, Ordering::Relaxed);
+
+        let (pesos_red, n_reducidos) = reducir_pesos(&pesos);
+        let distancia = evaluador.knn.distancia;
+        let incremento = distancia.factor(pesos_red[atributo]) -
+            distancia.factor(peso_reducido(self.pesos[atributo]));
+        self.sumas_candidato.clear();
+        if incremento != 0.0 {
+            self.sumas_candidato.extend(
+                self.sumas.iter().zip(diferencias.atributo(atributo).iter())
+                    .map(|(suma, termino)| suma + incremento * termino));
+        } else {
+            self.sumas_candidato.extend_from_slice(&self.sumas);
+        }
+
+        let tasa_red = tasa_reduccion(n_reducidos, pesos_red.len() as f32);
+        let valor = evaluador.evaluar_sumas(&self.sumas_candidato, tasa_red,
+                                            &mut self.fila);
+        self.candidato = Some(Cambio { atributo, peso, valor });
+        valor
+    }
+
+    // Pasa al último cambio evaluado
+    pub fn aceptar_cambio(&mut self) {
+        let cambio = self.candidato.take()
+            .expect("no se ha evaluado ningún cambio");
+        self.pesos[cambio.atributo] = cambio.peso;
+        self.valor = cambio.valor;
+        mem::swap(&mut self.sumas, &mut self.sumas_candidato);
+
+        // Cada tantos cambios aceptados como atributos se recalculan
+        // las sumas para que no se acumulen errores de redondeo
+        self.aceptados += 1;
+        if self.aceptados == self.pesos.len() {
+            self.aceptados = 0;
+            if let Some(ref diferencias) = self.evaluador.diferencias {
+                self.evaluador.sumas(diferencias,
+                                     &reducir_pesos(&self.pesos).0,
+                                     self.evaluador.hilos, &mut self.sumas);
+            }
+        }
+    }
+}
+
+// Suma a cada par del bloque, que empieza en el par indicado, sus
+// términos por los factores de los atributos
+fn sumar_pares(sumas: &mut [f32], inicio: usize, diferencias: &Diferencias,
+               factores: &[(usize, f32)]) {
+    for &(atributo, factor) in factores.iter() {
+        let terminos = &diferencias.atributo(atributo)[inicio..];
+        for (suma, termino) in sumas.iter_mut().zip(terminos.iter()) {
+            *suma += factor * termino;
+        }
+    }
+}
+
+fn calcular_diferencias(datos: Vista, knn: &Knn, num_pares: usize)
+                        -> Diferencias {
+    let mut terminos = vec![0.0; num_pares * datos.num_atributos()];
+
+    let mut par = 0;
+    for (i, miembro) in datos.iter().enumerate() {
+        for vecino in datos.iter().skip(i + 1) {
+            for (atributo, (a, b)) in miembro.get_attributes().iter()
+                .zip(vecino.get_attributes().iter()).enumerate() {
+                terminos[atributo * num_pares + par] =
+                    knn.distancia.termino(a - b);
+            }
+            par += 1;
+        }
+    }
+
+    Diferencias { num_pares, terminos }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+    use crate::datos::Muestras;
+    use crate::distancia::Distancia;
+
+    const DISTANCIAS: [Distancia; 4] = [
+        Distancia::Euclidea, Distancia::EuclideaCuadrado,
+        Distancia::Manhattan, Distancia::Minkowski(3.0),
+    ];
+
+    // Muestras aleatorias de tres clases. Los valores no se redondean,
+    // así no hay vecinos a la misma distancia que el orden de las sumas
+    // pueda desempatar de otra forma
+    fn muestras(rng: &mut StdRng, n: usize, num_atributos: usize)
+                -> Muestras {
+        let mut muestras = Muestras::new(num_atributos);
+        for _ in 0..n {
+            let atributos: Vec<f32> = (0..num_atributos)
+                .map(|_| rng.gen_range(0.0, 1.0))
+                .collect();
+            muestras.anadir(&atributos, rng.gen_range(0, 3));
+        }
+        muestras
+    }
+
+    fn pesos(rng: &mut StdRng, num_atributos: usize) -> Vec<f32> {
+        (0..num_atributos).map(|_| rng.gen_range(0.0, 1.0)).collect()
+    }
+
+    // Las distancias obtenidas de las sumas de los pares clasifican
+    // igual que el k-NN calculándolas directamente
+    #[test]
+    fn sumas_de_pares_clasifican_como_el_knn() {
+        let mut rng = StdRng::seed_from_u64(19);
+        let muestras = muestras(&mut rng, 60, 8);
+        let filas: Vec<usize> = (0..muestras.len()).collect();
+        let datos = muestras.vista(&filas);
+        let objetivo = Objetivo::default();
+
+        for &distancia in DISTANCIAS.iter() {
+            for &k in [1, 4].iter() {
+                let knn = Knn { k, distancia, ..Knn::default() };
+                let diferencias = Diferencias::calcular(datos, &knn)
+                    .unwrap();
+                let evaluador = Evaluador::new(datos, &objetivo, &knn, 3, 1,
+                                               None);
+                let mut sumas = Vec::new();
+                let mut fila = Vec::new();
+                for _ in 0..5 {
+                    let pesos_red = reducir_pesos(
+                        &pesos(&mut rng, datos.num_atributos())).0;
+                    evaluador.sumas(&diferencias, &pesos_red, 1, &mut sumas);
+                    assert_eq!(evaluador.predecir(&sumas, &mut fila),
+                               knn.predecir(datos, datos, Some(&pesos_red)),
+                               "{} con k = {}", distancia, k);
+                }
+            }
+        }
+    }
+
+    // La evaluación incremental da siempre el mismo valor que evaluar
+    // desde cero, tanto del candidato como de la solución actual tras
+    // aceptarlo o rechazarlo, también después de recalcular las sumas
+    #[test]
+    fn evaluacion_incremental_no_se_desvia() {
+        let mut rng = StdRng::seed_from_u64(20);
+        let muestras = muestras(&mut rng, 40, 6);
+        let filas: Vec<usize> = (0..muestras.len()).collect();
+        let datos = muestras.vista(&filas);
+        let objetivo = Objetivo::default();
+
+        for &distancia in DISTANCIAS.iter() {
+            let knn = Knn { k: 3, distancia, ..Knn::default() };
+            let diferencias = Diferencias::calcular(datos, &knn)
+                .map(Arc::new);
+            let evaluador = Evaluador::new(datos, &objetivo, &knn, 3, 1,
+                                           diferencias);
+            let mut incremental = evaluador.incremental(
+                &pesos(&mut rng, datos.num_atributos()));
+            assert_eq!(incremental.valor(),
+                       evaluador.evaluar(incremental.pesos()));
+
+            for _ in 0..50 {
+                let atributo = rng.gen_range(0, datos.num_atributos());
+                let peso = rng.gen_range(0.0, 1.0);
+                let mut pesos = incremental.pesos().to_vec();
+                pesos[atributo] = peso;
+                assert_eq!(incremental.evaluar_cambio(atributo, peso),
+                           evaluador.evaluar(&pesos), "{}", distancia);
+
+                if rng.gen() {
+                    incremental.aceptar_cambio();
+                    assert_eq!(incremental.pesos(), &pesos[..]);
+                }
+                assert_eq!(incremental.valor(),
+                           evaluador.evaluar(incremental.pesos()),
+                           "{}", distancia);
+            }
+        }
+    }
+
+    // Con varios hilos, tanto al repartir la población como los pares
+    // de una evaluación, se obtienen exactamente los mismos valores que
+    // con uno
+    #[test]
+    fn resultados_no_dependen_de_los_hilos() {
+        let mut rng = StdRng::seed_from_u64(21);
+        // Términos suficientes para repartir los pares entre 4 hilos
+        let muestras = muestras(&mut rng, 260, 34);
+        let filas: Vec<usize> = (0..muestras.len()).collect();
+        let datos = muestras.vista(&filas);
+        let objetivo = Objetivo::default();
+        let knn = Knn { k: 3, ..Knn::default() };
+        let diferencias = Diferencias::calcular(datos, &knn)
+            .map(Arc::new);
+        assert!(diferencias.is_some());
+        let poblacion: Vec<Vec<f32>> = (0..6)
+            .map(|_| pesos(&mut rng, datos.num_atributos()))
+            .collect();
+
+        let uno = Evaluador::new(datos, &objetivo, &knn, 3, 1,
+                                 diferencias.clone());
+        let cuatro = Evaluador::new(datos, &objetivo, &knn, 3, 4,
+                                    diferencias);
+        assert_eq!(uno.evaluar_poblacion(&poblacion),
+                   cuatro.evaluar_poblacion(&poblacion));
+        for cromosoma in poblacion.iter() {
+            assert_eq!(uno.evaluar(cromosoma), cuatro.evaluar(cromosoma));
+        }
+    }
+
+    // Con y sin los términos de los pares se obtienen los mismos
+    // valores, tanto evaluando desde cero como de forma incremental.
+    // Así no importa qué camino se elija
+    #[test]
+    fn con_y_sin_terminos_dan_lo_mismo() {
+        let mut rng = StdRng::seed_from_u64(22);
+        let muestras = muestras(&mut rng, 50, 5);
+        let filas: Vec<usize> = (0..muestras.len()).collect();
+        let datos = muestras.vista(&filas);
+        let objetivo = Objetivo::default();
+
+        for &distancia in DISTANCIAS.iter() {
+            for &k in [1, 3].iter() {
+                let knn = Knn { k, distancia, ..Knn::default() };
+                let diferencias = Diferencias::calcular(datos, &knn)
+                    .map(Arc::new);
+                assert!(diferencias.is_some());
+                let con = Evaluador::new(datos, &objetivo, &knn, 3, 2,
+                                         diferencias);
+                let sin = Evaluador::new(datos, &objetivo, &knn, 3, 2, None);
+
+                let poblacion: Vec<Vec<f32>> = (0..4)
+                    .map(|_| pesos(&mut rng, datos.num_atributos()))
+                    .collect();
+                assert_eq!(con.evaluar_poblacion(&poblacion),
+                           sin.evaluar_poblacion(&poblacion),
+                           "{} con k = {}", distancia, k);
+
+                let mut incremental_con = con.incremental(&poblacion[0]);
+                let mut incremental_sin = sin.incremental(&poblacion[0]);
+                for _ in 0..10 {
+                    let atributo = rng.gen_range(0, datos.num_atributos());
+                    let peso = rng.gen_range(0.0, 1.0);
+                    assert_eq!(incremental_con.evaluar_cambio(atributo, peso),
+                               incremental_sin.evaluar_cambio(atributo, peso),
+                               "{} con k = {}", distancia, k);
+                    incremental_con.aceptar_cambio();
+                    incremental_sin.aceptar_cambio();
+                }
+            }
+        }
+    }
+
+    // La decisión de calcular los términos sólo depende del conjunto
+    // y de la distancia, no de los hilos
+    #[test]
+    fn memoria_de_los_terminos() {
+        let knn = Knn::default();
+        // 3 pares de 2 atributos y, por cada suma, 3 pares y 3 filas
+        assert_eq!(Diferencias::memoria(3, 2, &knn, 1), Some((6 + 3 * 6) * 4));
+        assert_eq!(Diferencias::memoria(3, 2, &knn, 4), Some((6 + 6 * 6) * 4));
+        // Con muchos hilos ocupa más del máximo, pero se calculan
+        assert!(Diferencias::memoria(3000, 40, &knn, 1).is_some());
+        assert!(Diferencias::memoria(3000, 40, &knn, 64).unwrap() >
+                MEMORIA_MAXIMA_DIFERENCIAS);
+        assert_eq!(Diferencias::memoria(5000, 40, &knn, 1), None);
+        let coseno = Knn { distancia: Distancia::Coseno, ..Knn::default() };
+        assert_eq!(Diferencias::memoria(3, 2, &coseno, 1), None);
+    }
+}
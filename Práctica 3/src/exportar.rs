@@ -0,0 +1,211 @@
+// Exportación de los resultados a fichero
+//
+// Cada resultado (algoritmo, semilla, partición) se escribe como un
+// registro en CSV o en JSON Lines (un objeto JSON por línea) según la
+// extensión del fichero, para poder analizarlos con otras
+// herramientas. Los registros se escriben en cuanto se obtienen, de
+// forma que una ejecución interrumpida conserva lo ya calculado.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::metricas::MatrizConfusion;
+use crate::resumen::Resultado;
+
+// En CSV las listas (pesos, métricas por clase) se escriben en un
+// único campo, separadas por espacios, y las filas de la matriz de
+// confusión separadas por punto y coma
+const CABECERA: [&str; 23] = [
+    "dataset", "algoritmo", "variante", "semilla", "repeticion",
+    "particion", "escalado", "objetivo", "clasificador", "tasa_clas", "tasa_red", "f_objetivo",
+    "tiempo_ms", "evaluaciones", "tasa_bal", "f1_macro", "kappa", "mcc",
+    "precision", "exhaustividad", "f1", "matriz_confusion", "pesos",
+];
+
+enum Formato {
+    Csv(Box<csv::Writer<File>>),
+    JsonLines(BufWriter<File>),
+}
+
+pub struct Exportador {
+    formato: Formato,
+}
+
+impl Exportador {
+    // Crea el fichero. El formato se deduce de la extensión: .csv o
+    // .jsonl
+    pub fn new(path: &str) -> Result<Exportador, Box<dyn Error>> {
+        let formato = if path.ends_with(".csv") {
+            let mut escritor = csv::Writer::from_path(path)?;
+            escritor.write_record(CABECERA.iter())?;
+            escritor.flush()?;
+            Formato::Csv(Box::new(escritor))
+        } else if path.ends_with(".jsonl") {
+            Formato::JsonLines(BufWriter::new(File::create(path)?))
+        } else {
+            return Err(format!("{}: formato de exportación desconocido, \
+                                se admiten .csv y .jsonl", path).into());
+        };
+
+        Ok(Exportador { formato })
+    }
+
+    // Escribe el resultado de un algoritmo en una partición del
+    // conjunto de datos indicado, con el escalado, la función objetivo
+    // y el clasificador usados
+    pub fn escribir(&mut self, dataset: &str, escalado: &str, objetivo: &str,
+                    clasificador: &str, resultado: &Resultado) -> Result<(), Box<dyn Error>> {
+        let (algoritmo, variante) = match resultado.algoritmo.nombre()
+            .split_once('-') {
+            Some((algoritmo, variante)) => (algoritmo, variante),
+            None => (resultado.algoritmo.nombre(), ""),
+        };
+        let pesos = resultado.pesos.as_deref().unwrap_or(&[]);
+
+        let matriz = &resultado.matriz;
+        let por_clase = |metrica: fn(&MatrizConfusion, usize) -> f32| {
+            (0..matriz.num_clases())
+                .map(|c| metrica(matriz, c))
+                .collect::<Vec<f32>>()
+        };
+        let precision = por_clase(MatrizConfusion::precision);
+        let exhaustividad = por_clase(MatrizConfusion::exhaustividad);
+        let f1 = por_clase(MatrizConfusion::f1);
+        let filas_matriz: Vec<Vec<String>> = (0..matriz.num_clases())
+            .map(|real| (0..matriz.num_clases())
+                 .map(|predicha| matriz.celda(real, predicha).to_string())
+                 .collect())
+            .collect();
+
+        match self.formato {
+            Formato::Csv(ref mut escritor) => {
+                let lista = |valores: &[f32]| {
+                    valores.iter()
+                        .map(|v| v.to_string())
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                };
+                let filas_matriz: Vec<String> = filas_matriz.iter()
+                    .map(|fila| fila.join(" "))
+                    .collect();
+                escritor.write_record([
+                    dataset,
+                    algoritmo,
+                    variante,
+                    &resultado.semilla.to_string(),
+                    &resultado.repeticion.to_string(),
+                    &resultado.particion.to_string(),
+                    escalado,
+                    objetivo,
+                    clasificador,
+                    &resultado.tasa_clas.to_string(),
+                    &resultado.tasa_red.to_string(),
+                    &resultado.f_objetivo.to_string(),
+                    &format!("{:.3}", resultado.tiempo_ms),
+                    &resultado.evaluaciones.to_string(),
+                    &matriz.exactitud_balanceada().to_string(),
+                    &matriz.f1_macro().to_string(),
+                    &matriz.kappa().to_string(),
+                    &matriz.mcc().to_string(),
+                    &lista(&precision),
+                    &lista(&exhaustividad),
+                    &lista(&f1),
+                    &filas_matriz.join(";"),
+                    &lista(pesos),
+                ])?;
+                escritor.flush()?;
+            }
+            Formato::JsonLines(ref mut escritor) => {
+                let lista = |valores: &[f32]| {
+                    format!("[{}]",
+                            valores.iter()
+                            .map(|&v| numero_json(v))
+                            .collect::<Vec<String>>()
+                            .join(","))
+                };
+                let pesos = match resultado.pesos {
+                    Some(ref pesos) => lista(pesos),
+                    None => "null".to_string(),
+                };
+                let filas_matriz: Vec<String> = filas_matriz.iter()
+                    .map(|fila| format!("[{}]", fila.join(",")))
+                    .collect();
+                writeln!(escritor,
+                         "{{\"dataset\":{},\"algoritmo\":{},\"variante\":{},\
+                          \"semilla\":{},\"repeticion\":{},\"particion\":{},\
+                          \"escalado\":{},\"objetivo\":{},\"clasificador\":{},\
+                          \"tasa_clas\":{},\"tasa_red\":{},\"f_objetivo\":{},\"tiempo_ms\":{:.3},\
+                          \"evaluaciones\":{},\"tasa_bal\":{},\"f1_macro\":{},\
+                          \"kappa\":{},\"mcc\":{},\"precision\":{},\
+                          \"exhaustividad\":{},\"f1\":{},\
+                          \"matriz_confusion\":[{}],\"pesos\":{}}}",
+                         cadena_json(dataset), cadena_json(algoritmo),
+                         cadena_json(variante), resultado.semilla,
+                         resultado.repeticion, resultado.particion,
+                         cadena_json(escalado), cadena_json(objetivo),
+                         cadena_json(clasificador),
+                         numero_json(resultado.tasa_clas),
+                         numero_json(resultado.tasa_red),
+                         numero_json(resultado.f_objetivo),
+                         resultado.tiempo_ms, resultado.evaluaciones,
+                         numero_json(matriz.exactitud_balanceada()),
+                         numero_json(matriz.f1_macro()),
+                         numero_json(matriz.kappa()),
+                         numero_json(matriz.mcc()), lista(&precision),
+                         lista(&exhaustividad), lista(&f1),
+                         filas_matriz.join(","), pesos)?;
+                escritor.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Número JSON. JSON no admite NaN ni infinitos, así que se escriben
+// como null
+
+fn numero_json(valor: f32) -> String {
+    if valor.is_finite() {
+        valor.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+// Cadena JSON entre comillas, escapando los caracteres necesarios
+
+fn cadena_json(texto: &str) -> String {
+    let mut cadena = String::with_capacity(texto.len() + 2);
+    cadena.push('"');
+    for c in texto.chars() {
+        match c {
+            '"' => cadena.push_str("\\\""),
+            '\\' => cadena.push_str("\\\\"),
+            '\n' => cadena.push_str("\\n"),
+            '\r' => cadena.push_str("\\r"),
+            '\t' => cadena.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                cadena.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => cadena.push(c),
+        }
+    }
+    cadena.push('"');
+    cadena
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeros_no_finitos_como_null() {
+        assert_eq!(numero_json(0.25), "0.25");
+        assert_eq!(numero_json(-3.0), "-3");
+        assert_eq!(numero_json(f32::NAN), "null");
+        assert_eq!(numero_json(f32::INFINITY), "null");
+        assert_eq!(numero_json(f32::NEG_INFINITY), "null");
+    }
+}
@@ -0,0 +1,452 @@
+// Índice de vecinos más cercanos
+//
+// La búsqueda exhaustiva compara cada muestra con todo el conjunto de
+// entrenamiento, lo que es cuadrático en el número de muestras. Con
+// conjuntos grandes se puede usar en su lugar un árbol k-d, que divide
+// las muestras por la mediana del atributo con más recorrido hasta
+// dejar hojas pequeñas y descarta las ramas que no pueden tener un
+// vecino más cercano que los ya encontrados.
+//
+// El árbol se construye sin pesos, una sola vez por conjunto, y los
+// pesos se aplican en cada consulta: la diferencia ponderada en el
+// atributo de un corte es una cota inferior de la distancia a todas
+// las muestras del otro lado. Sirve para todas las distancias menos la
+// coseno, que no tiene esa cota.
+//
+// Al entrenar, el evaluador sólo lo usa cuando no puede guardar los
+// términos de las distancias entre las muestras (ver evaluador.rs),
+// porque con ellos cada evaluación es más rápida.
+//
+// Los vecinos son los mismos que con la búsqueda exhaustiva, también
+// entre vecinos a la misma distancia (se toma el primero en el
+// conjunto). La única diferencia es que la muestra consultada nunca
+// es vecina de sí misma, mientras que el 1-NN exhaustivo original
+// parte del primer miembro del entrenamiento aunque sea ella. Las
+// muestras a distancia NaN (con algún atributo ausente) no son vecinas.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::datos::{Muestra, Vista};
+use crate::distancia::Distancia;
+
+// Muestras por hoja, que se recorren de forma exhaustiva
+const TAM_HOJA: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TipoIndice {
+    Ninguno,
+    ArbolKd,
+}
+
+impl FromStr for TipoIndice {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<TipoIndice, String> {
+        match texto.to_lowercase().as_str() {
+            "ninguno" => Ok(TipoIndice::Ninguno),
+            "kd" => Ok(TipoIndice::ArbolKd),
+            _ => Err(format!("índice desconocido '{}', se admiten ninguno \
+                              y kd", texto)),
+        }
+    }
+}
+
+impl fmt::Display for TipoIndice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            TipoIndice::Ninguno => "ninguno",
+            TipoIndice::ArbolKd => "kd",
+        })
+    }
+}
+
+enum Nodo {
+    // Posiciones de sus muestras en el orden del árbol
+    Hoja { inicio: usize, fin: usize },
+    // A la izquierda las muestras con el atributo menor o igual que el
+    // corte y a la derecha las que lo tienen mayor o igual
+    Division { atributo: usize, corte: f32, izquierda: usize,
+               derecha: usize },
+}
+
+pub struct ArbolKd<'a> {
+    datos: Vista<'a>,
+    distancia: Distancia,
+    // Posiciones de las muestras en la vista, agrupadas por hojas
+    orden: Vec<usize>,
+    nodos: Vec<Nodo>,
+}
+
+impl<'a> ArbolKd<'a> {
+    // Árbol de las muestras de la vista para la distancia indicada, o
+    // None si la distancia no lo admite
+    pub fn new(datos: Vista<'a>, distancia: Distancia)
+               -> Option<ArbolKd<'a>> {
+        distancia.cota_atributo(0.0)?;
+
+        let mut arbol = ArbolKd { datos, distancia,
+                                  orden: (0..datos.len()).collect(),
+                                  nodos: Vec::new() };
+        if !datos.is_empty() {
+            arbol.construir(0, datos.len());
+        }
+        Some(arbol)
+    }
+
+    // Construye el subárbol de las muestras entre inicio y fin del
+    // orden y devuelve su nodo
+    fn construir(&mut self, inicio: usize, fin: usize) -> usize {
+        let nodo = self.nodos.len();
+        self.nodos.push(Nodo::Hoja { inicio, fin });
+
+        let (atributo, recorrido) = self.mayor_recorrido(inicio, fin);
+        if fin - inicio <= TAM_HOJA || recorrido <= 0.0 {
+            return nodo;
+        }
+
+        let datos = self.datos;
+        let valor = |posicion: usize| {
+            datos.muestra(posicion).get_attribute(atributo)
+        };
+        let mitad = (fin - inicio) / 2;
+        // Con el orden total los NaN quedan en los extremos sin romper
+        // la división; sus distancias son NaN y nunca son vecinos
+        self.orden[inicio..fin].select_nth_unstable_by(mitad, |&a, &b| {
+            valor(a).total_cmp(&valor(b))
+        });
+        let corte = valor(self.orden[inicio + mitad]);
+
+        let izquierda = self.construir(inicio, inicio + mitad);
+        let derecha = self.construir(inicio + mitad, fin);
+        self.nodos[nodo] = Nodo::Division { atributo, corte, izquierda,
+                                            derecha };
+        nodo
+    }
+
+    // Atributo con más diferencia entre el máximo y el mínimo de las
+    // muestras entre inicio y fin del orden, y esa diferencia
+    fn mayor_recorrido(&self, inicio: usize, fin: usize) -> (usize, f32) {
+        let mut mejor = (0, 0.0);
+        for atributo in 0..self.datos.num_atributos() {
+            let valores = self.orden[inicio..fin].iter()
+                .map(|&posicion| {
+                    self.datos.muestra(posicion).get_attribute(atributo)
+                });
+            let (minimo, maximo) = valores.fold(
+                (f32::INFINITY, f32::NEG_INFINITY),
+                |(min, max), valor| (min.min(valor), max.max(valor)));
+            if maximo - minimo > mejor.1 {
+                mejor = (atributo, maximo - minimo);
+            }
+        }
+        mejor
+    }
+
+    // Clase de la muestra en la posición indicada de la vista
+    pub fn clase(&self, posicion: usize) -> i32 {
+        self.datos.muestra(posicion).get_class()
+    }
+
+    // Los k vecinos más cercanos de la consulta (distancia y posición
+    // en la vista), de entre las muestras cuya posición cumple el
+    // filtro y que no son la propia consulta, de menor a mayor
+    // distancia
+    pub fn vecinos<F>(&self, consulta: Muestra, k: usize,
+                      pesos: Option<&[f32]>, filtro: F) -> Vec<(f32, usize)>
+        where F: Fn(usize) -> bool {
+        let mut busqueda = Busqueda { arbol: self, consulta, k, pesos,
+                                      filtro,
+                                      vecinos: Vec::with_capacity(k + 1) };
+        if k > 0 && !self.nodos.is_empty() {
+            busqueda.visitar(0);
+        }
+        busqueda.vecinos
+    }
+}
+
+struct Busqueda<'b, 'a, F> {
+    arbol: &'b ArbolKd<'a>,
+    consulta: Muestra<'b>,
+    k: usize,
+    pesos: Option<&'b [f32]>,
+    filtro: F,
+    vecinos: Vec<(f32, usize)>,
+}
+
+impl<'b, 'a, F> Busqueda<'b, 'a, F> where F: Fn(usize) -> bool {
+    fn visitar(&mut self, nodo: usize) {
+        match self.arbol.nodos[nodo] {
+            Nodo::Hoja { inicio, fin } => {
+                for i in inicio..fin {
+                    self.probar(self.arbol.orden[i]);
+                }
+            }
+            Nodo::Division { atributo, corte, izquierda, derecha } => {
+                let diferencia =
+                    self.consulta.get_attribute(atributo) - corte;
+                let (cercano, lejano) = if diferencia <= 0.0 {
+                    (izquierda, derecha)
+                } else {
+                    (derecha, izquierda)
+                };
+                self.visitar(cercano);
+
+                let peso = self.pesos.map_or(1.0, |p| p[atributo]);
+                let cota = self.arbol.distancia
+                    .cota_atributo(diferencia * peso)
+                    .unwrap_or(0.0);
+                // A la misma distancia que el peor puede haber alguno
+                // anterior en la vista. Si la cota es NaN (corte NaN) no
+                // se descarta nada
+                if self.vecinos.len() < self.k ||
+                    cota.partial_cmp(&self.vecinos[self.k - 1].0) !=
+                    Some(Ordering::Greater) {
+                    self.visitar(lejano);
+                }
+            }
+        }
+    }
+
+    fn probar(&mut self, posicion: usize) {
+        let vecino = self.arbol.datos.muestra(posicion);
+        if vecino.get_id() == self.consulta.get_id() ||
+            !(self.filtro)(posicion) {
+            return;
+        }
+
+        let distancia = match self.pesos {
+            Some(pesos) => self.arbol.distancia.ponderada(
+                self.consulta, vecino, pesos),
+            None => self.arbol.distancia.entre(self.consulta, vecino),
+        };
+        // Con algún atributo NaN la muestra no es vecina de ninguna
+        if distancia.is_nan() {
+            return;
+        }
+        let candidato = (distancia, posicion);
+        let antes = |otro: &(f32, usize)| {
+            candidato.0 < otro.0 || (candidato.0 == otro.0 &&
+                                     candidato.1 < otro.1)
+        };
+        if self.vecinos.len() == self.k &&
+            !antes(&self.vecinos[self.k - 1]) {
+            return;
+        }
+        let lugar = self.vecinos.iter()
+            .position(antes)
+            .unwrap_or(self.vecinos.len());
+        self.vecinos.insert(lugar, candidato);
+        self.vecinos.truncate(self.k);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+    use crate::datos::Muestras;
+    use crate::knn::{Knn, Votacion};
+
+    const DISTANCIAS: [Distancia; 5] = [
+        Distancia::Euclidea, Distancia::EuclideaCuadrado,
+        Distancia::Manhattan, Distancia::Minkowski(3.0), Distancia::Chebyshev,
+    ];
+
+    // Muestras aleatorias de tres clases. Los valores se redondean a
+    // décimas y se repiten algunas filas para que haya empates
+    fn muestras(rng: &mut StdRng, n: usize, num_atributos: usize)
+                -> Muestras {
+        let mut muestras = Muestras::new(num_atributos);
+        let mut anterior: Vec<f32> = Vec::new();
+        for i in 0..n {
+            let atributos: Vec<f32> = if i % 10 == 9 {
+                anterior.clone()
+            } else {
+                (0..num_atributos)
+                    .map(|_| (rng.gen_range(0.0, 1.0f32) * 10.0).round() / 10.0)
+                    .collect()
+            };
+            muestras.anadir(&atributos, rng.gen_range(0, 3));
+            anterior = atributos;
+        }
+        muestras
+    }
+
+    // Pesos aleatorios, algunos nulos como tras reducirlos
+    fn pesos(rng: &mut StdRng, num_atributos: usize) -> Vec<f32> {
+        (0..num_atributos)
+            .map(|_| {
+                let peso: f32 = rng.gen_range(0.0, 1.0);
+                if peso < 0.2 { 0.0 } else { peso }
+            })
+            .collect()
+    }
+
+    // Búsqueda exhaustiva de referencia: los k más cercanos de entre
+    // los que cumplen el filtro, sin la propia consulta, y a igual
+    // distancia el primero en la vista
+    fn exhaustiva<F>(datos: Vista, distancia: Distancia, consulta: Muestra,
+                     k: usize, pesos: Option<&[f32]>, filtro: F)
+                     -> Vec<(f32, usize)>
+        where F: Fn(usize) -> bool {
+        let mut candidatos: Vec<(f32, usize)> = datos.iter().enumerate()
+            .filter(|&(posicion, vecino)| {
+                vecino.get_id() != consulta.get_id() && filtro(posicion)
+            })
+            .map(|(posicion, vecino)| {
+                let d = match pesos {
+                    Some(pesos) => distancia.ponderada(consulta, vecino, pesos),
+                    None => distancia.entre(consulta, vecino),
+                };
+                (d, posicion)
+            })
+            .collect();
+        // Ordenación estable: a igual distancia, en el orden de la vista
+        candidatos.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        candidatos.truncate(k);
+        candidatos
+    }
+
+    #[test]
+    fn vecinos_como_la_busqueda_exhaustiva() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let muestras = muestras(&mut rng, 200, 5);
+        // Una vista desordenada y sin algunas filas
+        let mut filas: Vec<usize> = (0..muestras.len())
+            .filter(|fila| fila % 7 != 3)
+            .collect();
+        filas.shuffle(&mut rng);
+        let datos = muestras.vista(&filas);
+
+        for &distancia in DISTANCIAS.iter() {
+            let arbol = ArbolKd::new(datos, distancia).unwrap();
+            for _ in 0..2 {
+                let pesos = pesos(&mut rng, 5);
+                for pesos in [None, Some(pesos.as_slice())].iter().cloned() {
+                    for consulta in datos.iter() {
+                        for &k in [1, 4, 9].iter() {
+                            assert_eq!(
+                                arbol.vecinos(consulta, k, pesos, |_| true),
+                                exhaustiva(datos, distancia, consulta, k,
+                                           pesos, |_| true),
+                                "{} con k = {}", distancia, k);
+                        }
+
+                        // Amigo y enemigo más cercanos, como en RELIEF
+                        for &amigo in [true, false].iter() {
+                            let filtro = |posicion: usize| {
+                                (datos.muestra(posicion).get_class() ==
+                                 consulta.get_class()) == amigo
+                            };
+                            assert_eq!(
+                                arbol.vecinos(consulta, 1, pesos, filtro),
+                                exhaustiva(datos, distancia, consulta, 1,
+                                           pesos, filtro),
+                                "{}, amigo {}", distancia, amigo);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn consultas_de_fuera_del_arbol() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let muestras = muestras(&mut rng, 200, 3);
+        let entrenamiento: Vec<usize> = (0..150).collect();
+        let evaluacion: Vec<usize> = (150..200).collect();
+        let (entrenamiento, evaluacion) =
+            (muestras.vista(&entrenamiento), muestras.vista(&evaluacion));
+
+        for &distancia in DISTANCIAS.iter() {
+            let arbol = ArbolKd::new(entrenamiento, distancia).unwrap();
+            let pesos = pesos(&mut rng, 3);
+            for consulta in evaluacion.iter() {
+                assert_eq!(arbol.vecinos(consulta, 5, Some(&pesos), |_| true),
+                           exhaustiva(entrenamiento, distancia, consulta, 5,
+                                      Some(&pesos), |_| true));
+            }
+        }
+    }
+
+    #[test]
+    fn predicciones_como_la_busqueda_exhaustiva() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let muestras = muestras(&mut rng, 240, 4);
+        let entrenamiento: Vec<usize> = (0..180).collect();
+        let evaluacion: Vec<usize> = (180..240).collect();
+        let (entrenamiento, evaluacion) =
+            (muestras.vista(&entrenamiento), muestras.vista(&evaluacion));
+
+        for &distancia in DISTANCIAS.iter() {
+            for &(k, votacion) in [(1, Votacion::Mayoria),
+                                   (3, Votacion::Mayoria),
+                                   (5, Votacion::Distancia)].iter() {
+                let exhaustivo = Knn { k, votacion, distancia,
+                                       ..Knn::default() };
+                let con_indice = Knn { indice: TipoIndice::ArbolKd,
+                                       ..exhaustivo };
+                let arbol = con_indice.arbol(entrenamiento).unwrap();
+                let pesos = pesos(&mut rng, 4);
+
+                for pesos in [None, Some(pesos.as_slice())].iter().cloned() {
+                    let referencia = exhaustivo.predecir(
+                        entrenamiento, evaluacion, pesos);
+                    assert_eq!(con_indice.predecir_con_arbol(
+                        &arbol, evaluacion, pesos), referencia);
+                    assert_eq!(con_indice.predecir(
+                        entrenamiento, evaluacion, pesos), referencia);
+                }
+
+                // Dejando uno fuera
+                for pesos in [None, Some(pesos.as_slice())].iter().cloned() {
+                    assert_eq!(
+                        con_indice.predecir_con_arbol(&arbol, entrenamiento,
+                                                      pesos),
+                        exhaustivo.predecir(entrenamiento, entrenamiento,
+                                            pesos));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn coseno_sin_arbol() {
+        let muestras = Muestras::new(2);
+        let filas: Vec<usize> = Vec::new();
+        assert!(ArbolKd::new(muestras.vista(&filas), Distancia::Coseno)
+                .is_none());
+    }
+
+    #[test]
+    fn valores_nan() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut muestras = muestras(&mut rng, 100, 3);
+        for (fila, atributos) in muestras.filas_mut().enumerate() {
+            if fila % 5 == 0 {
+                atributos[fila % 3] = f32::NAN;
+            }
+        }
+        let filas: Vec<usize> = (0..muestras.len()).collect();
+        let datos = muestras.vista(&filas);
+
+        // Las muestras con NaN nunca son vecinas, y las demás lo son
+        // como en la búsqueda exhaustiva
+        let arbol = ArbolKd::new(datos, Distancia::Euclidea).unwrap();
+        let sin_nan = |posicion: usize| {
+            !datos.muestra(posicion).get_attributes().iter()
+                .any(|v| v.is_nan())
+        };
+        for (posicion, consulta) in datos.iter().enumerate() {
+            if sin_nan(posicion) {
+                assert_eq!(arbol.vecinos(consulta, 3, None, |_| true),
+                           exhaustiva(datos, Distancia::Euclidea, consulta,
+                                      3, None, sin_nan));
+            }
+        }
+    }
+}
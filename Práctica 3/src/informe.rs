@@ -0,0 +1,330 @@
+// Tablas de resultados para las memorias
+//
+// Para cada conjunto de datos se genera una tabla con una fila por
+// partición (y semilla o repetición, si hay varias) más la fila de
+// medias, y las columnas T_clas, T_red, Agr. y T de cada algoritmo.
+// Le sigue otra con las medias de las métricas por clase (tasa
+// balanceada, F1 macro, kappa y MCC) de cada algoritmo. Al final se
+// añade una tabla comparativa con las medias de cada algoritmo en
+// todos los conjuntos de datos. Se escriben en LaTeX
+// (tabular) o en Markdown según la extensión del fichero.
+
+use std::error::Error;
+use std::fs;
+
+use crate::resumen::{Estadisticas, Resultado, Resumen};
+
+// Columnas de cada algoritmo (o conjunto de datos en la comparativa).
+// El tiempo se da en segundos
+const COLUMNAS: [&str; 4] = ["T_clas", "T_red", "Agr.", "T"];
+
+const COLUMNAS_METRICAS: [&str; 4] = ["T_bal", "F1_macro", "Kappa", "MCC"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Formato {
+    Latex,
+    Markdown,
+}
+
+// Tabla con las columnas agrupadas: cada grupo tiene las mismas
+// columnas. Si la última fila es la de medias se separa del resto
+struct Tabla {
+    titulo: String,
+    grupos: Vec<String>,
+    columnas: &'static [&'static str],
+    filas: Vec<(String, Vec<String>)>,
+    fila_media: bool,
+}
+
+// Escribe el informe con los resultados de todos los conjuntos de datos
+
+pub fn escribir_informe(path: &str, resumenes: &[Resumen])
+                        -> Result<(), Box<dyn Error>> {
+    let formato = if path.ends_with(".tex") {
+        Formato::Latex
+    } else if path.ends_with(".md") {
+        Formato::Markdown
+    } else {
+        return Err(format!("{}: formato de informe desconocido, se \
+                            admiten .tex y .md", path).into());
+    };
+
+    let mut tablas: Vec<Tabla> = Vec::new();
+    for resumen in resumenes.iter() {
+        tablas.push(tabla_dataset(resumen));
+        tablas.push(tabla_metricas(resumen));
+    }
+    if !resumenes.is_empty() {
+        tablas.push(tabla_comparativa(resumenes));
+    }
+
+    let contenido = tablas.iter()
+        .map(|tabla| match formato {
+            Formato::Latex => tabla_latex(tabla),
+            Formato::Markdown => tabla_markdown(tabla),
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    fs::write(path, contenido)?;
+    Ok(())
+}
+
+// Valores de las columnas para un resultado
+
+fn celdas(resultado: &Resultado) -> Vec<String> {
+    vec![format!("{:.2}", resultado.tasa_clas),
+         format!("{:.2}", resultado.tasa_red),
+         format!("{:.2}", resultado.f_objetivo),
+         format!("{:.3}", resultado.tiempo_ms / 1000.0)]
+}
+
+// Medias de las columnas para varios resultados
+
+fn celdas_media(resultados: &[&Resultado]) -> Vec<String> {
+    let media = |valor: fn(&Resultado) -> f64| {
+        let valores: Vec<f64> = resultados.iter().map(|r| valor(r)).collect();
+        Estadisticas::calcular(&valores).media
+    };
+
+    vec![format!("{:.2}", media(|r| r.tasa_clas as f64)),
+         format!("{:.2}", media(|r| r.tasa_red as f64)),
+         format!("{:.2}", media(|r| r.f_objetivo as f64)),
+         format!("{:.3}", media(|r| r.tiempo_ms) / 1000.0)]
+}
+
+// Tabla de un conjunto de datos: una fila por partición
+
+fn tabla_dataset(resumen: &Resumen) -> Tabla {
+    let algoritmos = resumen.algoritmos();
+    let resultados: Vec<Vec<&Resultado>> = algoritmos.iter()
+        .map(|&algoritmo| resumen.resultados_de(algoritmo))
+        .collect();
+
+    // Las filas siguen el orden de ejecución del primer algoritmo. Si
+    // hay varias semillas o repeticiones se indican en la etiqueta
+    let claves: Vec<(u64, usize, usize)> = resultados.first()
+        .map(|primero| primero.iter()
+             .map(|r| (r.semilla, r.repeticion, r.particion))
+             .collect())
+        .unwrap_or_default();
+    let varias_ejecuciones = claves.iter()
+        .any(|&(semilla, repeticion, _)|
+             semilla != claves[0].0 || repeticion != claves[0].1);
+
+    let mut filas: Vec<(String, Vec<String>)> = Vec::new();
+    for &(semilla, repeticion, particion) in claves.iter() {
+        let etiqueta = if varias_ejecuciones {
+            format!("S{} R{} P{}", semilla, repeticion + 1, particion + 1)
+        } else {
+            format!("Partición {}", particion + 1)
+        };
+
+        let mut fila: Vec<String> = Vec::new();
+        for resultados_algoritmo in resultados.iter() {
+            match resultados_algoritmo.iter().find(|r| {
+                r.semilla == semilla && r.repeticion == repeticion &&
+                    r.particion == particion
+            }) {
+                Some(resultado) => fila.extend(celdas(resultado)),
+                None => fila.extend(vec!["-".to_string(); COLUMNAS.len()]),
+            }
+        }
+        filas.push((etiqueta, fila));
+    }
+
+    let media: Vec<String> = resultados.iter()
+        .flat_map(|r| celdas_media(r))
+        .collect();
+    filas.push(("Media".to_string(), media));
+
+    Tabla {
+        titulo: format!("Resultados en {}", resumen.dataset()),
+        grupos: algoritmos.iter().map(|a| a.nombre().to_string()).collect(),
+        columnas: &COLUMNAS,
+        filas,
+        fila_media: true,
+    }
+}
+
+// Comparativa global: una fila por algoritmo con sus medias en cada
+// conjunto de datos
+
+fn tabla_comparativa(resumenes: &[Resumen]) -> Tabla {
+    let mut algoritmos = Vec::new();
+    for resumen in resumenes.iter() {
+        for algoritmo in resumen.algoritmos() {
+            if !algoritmos.contains(&algoritmo) {
+                algoritmos.push(algoritmo);
+            }
+        }
+    }
+
+    let filas = algoritmos.iter()
+        .map(|&algoritmo| {
+            let fila = resumenes.iter()
+                .flat_map(|resumen| {
+                    let resultados = resumen.resultados_de(algoritmo);
+                    if resultados.is_empty() {
+                        vec!["-".to_string(); COLUMNAS.len()]
+                    } else {
+                        celdas_media(&resultados)
+                    }
+                })
+                .collect();
+            (algoritmo.nombre().to_string(), fila)
+        })
+        .collect();
+
+    Tabla {
+        titulo: "Comparativa global (medias)".to_string(),
+        grupos: resumenes.iter().map(|r| r.dataset().to_string()).collect(),
+        columnas: &COLUMNAS,
+        filas,
+        fila_media: false,
+    }
+}
+
+// Métricas en validación de un conjunto de datos: una fila por
+// algoritmo con las medias de todas sus particiones
+
+fn tabla_metricas(resumen: &Resumen) -> Tabla {
+    let filas = resumen.algoritmos().iter()
+        .map(|&algoritmo| {
+            let resultados = resumen.resultados_de(algoritmo);
+            let media = |valor: fn(&Resultado) -> f32| {
+                let valores: Vec<f64> = resultados.iter()
+                    .map(|r| valor(r) as f64)
+                    .collect();
+                Estadisticas::calcular(&valores).media
+            };
+            let fila = vec![
+                format!("{:.2}", media(|r| r.matriz.exactitud_balanceada())),
+                format!("{:.2}", media(|r| r.matriz.f1_macro())),
+                format!("{:.4}", media(|r| r.matriz.kappa())),
+                format!("{:.4}", media(|r| r.matriz.mcc())),
+            ];
+            (algoritmo.nombre().to_string(), fila)
+        })
+        .collect();
+
+    Tabla {
+        titulo: format!("Métricas en {} (medias)", resumen.dataset()),
+        grupos: vec![resumen.dataset().to_string()],
+        columnas: &COLUMNAS_METRICAS,
+        filas,
+        fila_media: false,
+    }
+}
+
+fn tabla_latex(tabla: &Tabla) -> String {
+    let mut texto = String::new();
+    let columnas_grupo = "c".repeat(tabla.columnas.len());
+    let formato_columnas: Vec<String> =
+        tabla.grupos.iter().map(|_| columnas_grupo.clone()).collect();
+
+    texto.push_str(&format!("% {}\n", tabla.titulo));
+    texto.push_str(&format!("\\begin{{tabular}}{{l|{}}}\n",
+                            formato_columnas.join("|")));
+    texto.push_str("\\hline\n");
+
+    let cabecera_grupos: Vec<String> = tabla.grupos.iter().enumerate()
+        .map(|(i, grupo)| format!(
+            "\\multicolumn{{{}}}{{c{}}}{{{}}}", tabla.columnas.len(),
+            if i + 1 < tabla.grupos.len() { "|" } else { "" },
+            escapar_latex(grupo)))
+        .collect();
+    texto.push_str(&format!(" & {} \\\\\n", cabecera_grupos.join(" & ")));
+
+    let cabecera_columnas: Vec<String> = tabla.grupos.iter()
+        .flat_map(|_| tabla.columnas.iter().map(|c| escapar_latex(c)))
+        .collect();
+    texto.push_str(&format!(" & {} \\\\\n", cabecera_columnas.join(" & ")));
+    texto.push_str("\\hline\n");
+
+    for (i, (etiqueta, celdas)) in tabla.filas.iter().enumerate() {
+        if tabla.fila_media && i + 1 == tabla.filas.len() {
+            texto.push_str("\\hline\n");
+        }
+        texto.push_str(&format!("{} & {} \\\\\n", escapar_latex(etiqueta),
+                                celdas.join(" & ")));
+    }
+
+    texto.push_str("\\hline\n\\end{tabular}\n");
+    texto
+}
+
+fn tabla_markdown(tabla: &Tabla) -> String {
+    let mut texto = String::new();
+
+    texto.push_str(&format!("### {}\n\n", tabla.titulo));
+
+    let cabecera: Vec<String> = tabla.grupos.iter()
+        .flat_map(|grupo| tabla.columnas.iter()
+                  .map(move |columna| {
+                      escapar_markdown(&format!("{} {}", grupo, columna))
+                  }))
+        .collect();
+    texto.push_str(&format!("| | {} |\n", cabecera.join(" | ")));
+    texto.push_str(&format!("|---|{}\n", "---:|".repeat(cabecera.len())));
+
+    for (i, (etiqueta, celdas)) in tabla.filas.iter().enumerate() {
+        let etiqueta = escapar_markdown(etiqueta);
+        let etiqueta = if tabla.fila_media && i + 1 == tabla.filas.len() {
+            format!("**{}**", etiqueta)
+        } else {
+            etiqueta
+        };
+        texto.push_str(&format!("| {} | {} |\n", etiqueta, celdas.join(" | ")));
+    }
+
+    texto
+}
+
+// Los caracteres especiales de LaTeX. La barra invertida, el
+// circunflejo y la virgulilla no se pueden escapar con una barra, así
+// que se escriben con sus órdenes de texto
+
+fn escapar_latex(texto: &str) -> String {
+    let mut escapado = String::with_capacity(texto.len());
+    for c in texto.chars() {
+        match c {
+            '_' | '%' | '&' | '#' | '$' | '{' | '}' => {
+                escapado.push('\\');
+                escapado.push(c);
+            }
+            '\\' => escapado.push_str("\\textbackslash{}"),
+            '^' => escapado.push_str("\\textasciicircum{}"),
+            '~' => escapado.push_str("\\textasciitilde{}"),
+            c => escapado.push(c),
+        }
+    }
+    escapado
+}
+
+// En una celda de una tabla Markdown la barra vertical separaría
+// columnas
+
+fn escapar_markdown(texto: &str) -> String {
+    texto.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapa_caracteres_especiales_de_latex() {
+        assert_eq!(escapar_latex("T_clas 50% & #1"),
+                   "T\\_clas 50\\% \\& \\#1");
+        assert_eq!(escapar_latex("{$x$}"), "\\{\\$x\\$\\}");
+        assert_eq!(escapar_latex("a\\b^c~d"),
+                   "a\\textbackslash{}b\\textasciicircum{}c\\textasciitilde{}d");
+    }
+
+    #[test]
+    fn escapa_barras_en_celdas_markdown() {
+        assert_eq!(escapar_markdown("a|b||c"), "a\\|b\\|\\|c");
+        assert_eq!(escapar_markdown("ionosphere"), "ionosphere");
+    }
+}
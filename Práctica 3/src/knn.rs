@@ -0,0 +1,386 @@
+// Clasificador k-NN
+//
+// El 1-NN es muy sensible al ruido (por ejemplo en ionosphere). El
+// clasificador usado en validación y dentro de la función objetivo es
+// un k-NN con k configurable y votación por mayoría o ponderada por la
+// inversa de la distancia. Con k = 1 se usa el 1-NN original. Los
+// vecinos se buscan de forma exhaustiva o con un árbol k-d.
+//
+// Los resultados son deterministas:
+//  - entre vecinos a la misma distancia se toma el primero en el
+//    conjunto de entrenamiento
+//  - si varias clases empatan en la votación se elige según la regla
+//    de desempate: la del vecino más cercano de entre ellas o la de
+//    menor índice (orden de aparición en el fichero)
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::datos::{Muestra, Vista};
+use crate::distancia::Distancia;
+use crate::indice::{ArbolKd, TipoIndice};
+use crate::{predecir_1nn, predecir_1nn_con_pesos};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Votacion {
+    Mayoria,
+    Distancia,
+}
+
+impl FromStr for Votacion {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<Votacion, String> {
+        match texto.to_lowercase().as_str() {
+            "mayoria" | "mayoría" => Ok(Votacion::Mayoria),
+            "distancia" => Ok(Votacion::Distancia),
+            _ => Err(format!("votación desconocida '{}', se admiten \
+                              mayoria y distancia", texto)),
+        }
+    }
+}
+
+impl fmt::Display for Votacion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Votacion::Mayoria => "mayoria",
+            Votacion::Distancia => "distancia",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Desempate {
+    Cercano,
+    Menor,
+}
+
+impl FromStr for Desempate {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<Desempate, String> {
+        match texto.to_lowercase().as_str() {
+            "cercano" => Ok(Desempate::Cercano),
+            "menor" => Ok(Desempate::Menor),
+            _ => Err(format!("desempate desconocido '{}', se admiten \
+                              cercano y menor", texto)),
+        }
+    }
+}
+
+impl fmt::Display for Desempate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Desempate::Cercano => "cercano",
+            Desempate::Menor => "menor",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Knn {
+    pub k: usize,
+    pub votacion: Votacion,
+    pub desempate: Desempate,
+    pub distancia: Distancia,
+    pub indice: TipoIndice,
+}
+
+impl Default for Knn {
+    fn default() -> Knn {
+        Knn {
+            k: 1,
+            votacion: Votacion::Mayoria,
+            desempate: Desempate::Cercano,
+            distancia: Distancia::Euclidea,
+            indice: TipoIndice::Ninguno,
+        }
+    }
+}
+
+impl Knn {
+    // Clase asignada a cada miembro del conjunto de evaluación, con
+    // los pesos (ya reducidos) indicados o sin pesos. Un miembro nunca
+    // se cuenta como vecino de sí mismo, así se puede clasificar el
+    // conjunto de entrenamiento dejando uno fuera
+    pub fn predecir(&self, set_entrenamiento: Vista,
+                    set_evaluacion: Vista, pesos_red: Option<&[f32]>)
+                    -> Vec<i32> {
+        if let Some(arbol) = self.arbol(set_entrenamiento) {
+            return self.predecir_con_arbol(&arbol, set_evaluacion,
+                                           pesos_red);
+        }
+
+        if self.k == 1 {
+            return match pesos_red {
+                Some(pesos_red) => predecir_1nn_con_pesos(
+                    set_entrenamiento, set_evaluacion, pesos_red,
+                    self.distancia),
+                None => predecir_1nn(set_entrenamiento, set_evaluacion,
+                                     self.distancia),
+            };
+        }
+
+        set_evaluacion.iter()
+            .map(|miembro| {
+                let vecinos = self.vecinos(miembro, set_entrenamiento,
+                                           pesos_red);
+                self.votar(&vecinos)
+            })
+            .collect()
+    }
+
+    // Árbol k-d del conjunto de entrenamiento, si se ha elegido ese
+    // índice y la distancia lo admite
+    pub fn arbol<'a>(&self, set_entrenamiento: Vista<'a>)
+                     -> Option<ArbolKd<'a>> {
+        match self.indice {
+            TipoIndice::ArbolKd => {
+                ArbolKd::new(set_entrenamiento, self.distancia)
+            }
+            TipoIndice::Ninguno => None,
+        }
+    }
+
+    // Como predecir, buscando los vecinos en el árbol del conjunto de
+    // entrenamiento
+    pub fn predecir_con_arbol(&self, arbol: &ArbolKd,
+                              set_evaluacion: Vista,
+                              pesos_red: Option<&[f32]>) -> Vec<i32> {
+        set_evaluacion.iter()
+            .map(|miembro| {
+                let vecinos: Vec<(f32, i32)> = arbol
+                    .vecinos(miembro, self.k, pesos_red, |_| true)
+                    .into_iter()
+                    .map(|(d, posicion)| (d, arbol.clase(posicion)))
+                    .collect();
+                self.votar(&vecinos)
+            })
+            .collect()
+    }
+
+    // Los k vecinos más cercanos (distancia y clase) del miembro, de
+    // menor a mayor distancia
+    fn vecinos(&self, miembro: Muestra, set_entrenamiento: Vista,
+               pesos_red: Option<&[f32]>) -> Vec<(f32, i32)> {
+        let candidatos = set_entrenamiento.iter()
+            .filter(|vecino| miembro.get_id() != vecino.get_id())
+            .map(|vecino| {
+                let distancia = match pesos_red {
+                    Some(pesos_red) => self.distancia.ponderada(
+                        miembro, vecino, pesos_red),
+                    None => self.distancia.entre(miembro, vecino),
+                };
+                (distancia, vecino.get_class())
+            });
+
+        self.mas_cercanos(candidatos)
+    }
+
+    // Los k más cercanos de entre los candidatos (distancia y clase),
+    // de menor a mayor distancia
+    pub fn mas_cercanos<I>(&self, candidatos: I) -> Vec<(f32, i32)>
+        where I: Iterator<Item = (f32, i32)> {
+        let mut vecinos: Vec<(f32, i32)> = Vec::with_capacity(self.k + 1);
+
+        for (distancia, clase) in candidatos {
+            if vecinos.len() == self.k &&
+                distancia >= vecinos[self.k - 1].0 {
+                continue;
+            }
+            // Tras los que están a la misma distancia
+            let posicion = vecinos.iter()
+                .position(|&(d, _)| distancia < d)
+                .unwrap_or(vecinos.len());
+            vecinos.insert(posicion, (distancia, clase));
+            vecinos.truncate(self.k);
+        }
+
+        vecinos
+    }
+
+    // Clase elegida por los vecinos
+    pub fn votar(&self, vecinos: &[(f32, i32)]) -> i32 {
+        // Con votación por distancia, si hay vecinos a distancia cero
+        // sólo votan ellos
+        let hay_coincidentes = vecinos.iter().any(|&(d, _)| d == 0.0);
+        let mut votos: Vec<(i32, f32)> = Vec::new();
+        for &(distancia, clase) in vecinos.iter() {
+            let voto = match self.votacion {
+                Votacion::Mayoria => 1.0,
+                Votacion::Distancia if hay_coincidentes => {
+                    if distancia == 0.0 { 1.0 } else { 0.0 }
+                }
+                Votacion::Distancia => 1.0 / distancia,
+            };
+            match votos.iter_mut().find(|(c, _)| *c == clase) {
+                Some((_, total)) => *total += voto,
+                None => votos.push((clase, voto)),
+            }
+        }
+
+        // Las clases están en orden de su vecino más cercano
+        let maximo = votos.iter().map(|&(_, v)| v).fold(0.0, f32::max);
+        let mut empatadas = votos.iter()
+            .filter(|&&(_, v)| v == maximo)
+            .map(|&(c, _)| c);
+        match self.desempate {
+            Desempate::Cercano => empatadas.next(),
+            Desempate::Menor => empatadas.min(),
+        }.unwrap_or(0)
+    }
+}
+
+impl fmt::Display for Knn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.k == 1 {
+            write!(f, "1-NN (distancia {}", self.distancia)?;
+        } else {
+            write!(f, "{}-NN (distancia {}, votación {}, desempate {}",
+                   self.k, self.distancia, self.votacion, self.desempate)?;
+        }
+        if self.indice == TipoIndice::ArbolKd {
+            write!(f, ", árbol k-d")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datos::Muestras;
+
+    fn knn(k: usize, votacion: Votacion, desempate: Desempate) -> Knn {
+        Knn { k, votacion, desempate, ..Knn::default() }
+    }
+
+    #[test]
+    fn mas_cercanos_en_orden_y_el_primero_a_igual_distancia() {
+        let candidatos = [(3.0, 0), (1.0, 1), (1.0, 2), (2.0, 3)];
+        let knn2 = knn(2, Votacion::Mayoria, Desempate::Cercano);
+        assert_eq!(knn2.mas_cercanos(candidatos.iter().cloned()),
+                   vec![(1.0, 1), (1.0, 2)]);
+        let knn1 = knn(1, Votacion::Mayoria, Desempate::Cercano);
+        assert_eq!(knn1.mas_cercanos(candidatos.iter().cloned()),
+                   vec![(1.0, 1)]);
+        // Con menos candidatos que k se devuelven todos
+        let knn9 = knn(9, Votacion::Mayoria, Desempate::Cercano);
+        assert_eq!(knn9.mas_cercanos(candidatos.iter().cloned()).len(), 4);
+    }
+
+    #[test]
+    fn votaciones_y_desempates() {
+        let mayoria = knn(4, Votacion::Mayoria, Desempate::Cercano);
+        let distancia = knn(4, Votacion::Distancia, Desempate::Cercano);
+        let menor = knn(4, Votacion::Mayoria, Desempate::Menor);
+
+        let vecinos = [(0.1, 2), (0.2, 1), (0.3, 1)];
+        assert_eq!(mayoria.votar(&vecinos), 1);
+        // 1/0.1 frente a 1/0.2 + 1/0.3
+        assert_eq!(distancia.votar(&vecinos), 2);
+
+        // Empate a dos votos: la del vecino más cercano o la menor
+        let vecinos = [(0.1, 2), (0.2, 1), (0.3, 1), (0.4, 2)];
+        assert_eq!(mayoria.votar(&vecinos), 2);
+        assert_eq!(menor.votar(&vecinos), 1);
+
+        // Los vecinos a distancia cero deciden solos
+        let vecinos = [(0.0, 1), (0.01, 2), (0.02, 2)];
+        assert_eq!(distancia.votar(&vecinos), 1);
+        let vecinos = [(0.0, 3), (0.0, 0), (0.01, 2)];
+        assert_eq!(distancia.votar(&vecinos), 3);
+        let desempate_menor = knn(4, Votacion::Distancia, Desempate::Menor);
+        assert_eq!(desempate_menor.votar(&vecinos), 0);
+    }
+
+    #[test]
+    fn predice_sin_contarse_como_vecino() {
+        let mut muestras = Muestras::new(1);
+        for &(x, clase) in [(0.0, 0), (0.1, 0), (0.5, 1), (0.6, 1),
+                            (1.0, 2), (0.45, 0), (0.2, 1), (0.9, 0)].iter() {
+            muestras.anadir(&[x], clase);
+        }
+        let filas_entrenamiento = [0, 1, 2, 3, 4];
+        let filas_evaluacion = [5, 6, 7];
+        let entrenamiento = muestras.vista(&filas_entrenamiento);
+        let evaluacion = muestras.vista(&filas_evaluacion);
+
+        let mayoria = knn(3, Votacion::Mayoria, Desempate::Cercano);
+        assert_eq!(mayoria.predecir(entrenamiento, evaluacion, None),
+                   vec![1, 0, 1]);
+        let distancia = knn(3, Votacion::Distancia, Desempate::Cercano);
+        assert_eq!(distancia.predecir(entrenamiento, evaluacion, None),
+                   vec![1, 0, 2]);
+        let uno = Knn::default();
+        assert_eq!(uno.predecir(entrenamiento, evaluacion, None),
+                   vec![1, 0, 2]);
+
+        // Dejando uno fuera, cada muestra de entrenamiento se clasifica
+        // con las demás
+        assert_eq!(uno.predecir(entrenamiento, entrenamiento, None),
+                   vec![0, 0, 1, 1, 1]);
+        assert_eq!(uno.predecir(entrenamiento, entrenamiento, Some(&[1.0])),
+                   vec![0, 0, 1, 1, 1]);
+        // Con peso nulo en el único atributo todas están a distancia
+        // cero, y votan las tres primeras sin contar la propia
+        assert_eq!(mayoria.predecir(entrenamiento, entrenamiento,
+                                    Some(&[0.0])),
+                   vec![1, 1, 0, 0, 0]);
+        // Y con k = 1 gana el primero que no sea la propia muestra,
+        // aunque ésta vaya delante
+        let filas_empatadas = [4, 0, 2];
+        assert_eq!(uno.predecir(muestras.vista(&filas_empatadas),
+                                muestras.vista(&filas_empatadas),
+                                Some(&[0.0])),
+                   vec![0, 2, 2]);
+    }
+
+    // El 1-NN original debe elegir el mismo vecino que mas_cercanos
+    // con k = 1, también con empates y dejando uno fuera
+    #[test]
+    fn uno_nn_como_el_primero_de_mas_cercanos() {
+        let mut muestras = Muestras::new(2);
+        for i in 0..12i32 {
+            muestras.anadir(&[(i % 3) as f32, (i % 4) as f32 * 0.5],
+                            i % 5);
+        }
+        let filas_entrenamiento = [3, 0, 7, 1, 10, 4, 8, 11];
+        let filas_evaluacion = [2, 5, 6, 9];
+        let entrenamiento = muestras.vista(&filas_entrenamiento);
+        let uno = Knn::default();
+
+        for &pesos in [None, Some(&[1.0, 0.5][..]), Some(&[0.0, 1.0][..]),
+                       Some(&[0.0, 0.0][..])].iter() {
+            for &filas in [&filas_evaluacion[..],
+                           &filas_entrenamiento[..]].iter() {
+                let evaluacion = muestras.vista(filas);
+                let esperadas: Vec<i32> = evaluacion.iter()
+                    .map(|miembro| {
+                        let candidatos = entrenamiento.iter()
+                            .filter(|v| v.get_id() != miembro.get_id())
+                            .map(|v| (match pesos {
+                                Some(p) => uno.distancia.ponderada(
+                                    miembro, v, p),
+                                None => uno.distancia.entre(miembro, v),
+                            }, v.get_class()));
+                        uno.mas_cercanos(candidatos)[0].1
+                    })
+                    .collect();
+                assert_eq!(uno.predecir(entrenamiento, evaluacion, pesos),
+                           esperadas);
+            }
+        }
+    }
+
+    #[test]
+    fn opciones_desde_texto() {
+        assert_eq!("Mayoría".parse(), Ok(Votacion::Mayoria));
+        assert_eq!("distancia".parse(), Ok(Votacion::Distancia));
+        assert!("otra".parse::<Votacion>().is_err());
+        assert_eq!("MENOR".parse(), Ok(Desempate::Menor));
+        assert!("".parse::<Desempate>().is_err());
+        assert_eq!(Votacion::Distancia.to_string().parse(),
+                   Ok(Votacion::Distancia));
+    }
+}
@@ -1,6 +1,69 @@
 // Leer csv
 extern crate csv;
 
+// Leer arff
+mod arff;
+
+// Conjuntos de datos
+mod datos;
+use datos::{Imputador, Muestras, Vista};
+
+// Escalado de los atributos
+mod escalado;
+use escalado::{Escalador, TipoEscalado};
+
+// Evaluación de la función objetivo
+mod evaluador;
+use evaluador::{Diferencias, EvaluacionIncremental, Evaluador,
+                MEMORIA_MAXIMA_DIFERENCIAS};
+
+// Comparación estadística de los algoritmos
+mod estadistica;
+
+// Exportación de resultados
+mod exportar;
+use exportar::Exportador;
+
+// Tablas de resultados para las memorias
+mod informe;
+
+// Resumen de resultados
+mod resumen;
+use resumen::{Resultado, Resumen};
+
+// Derivación de semillas
+mod semillas;
+use semillas::{componente_texto, derivar_semilla};
+
+// Matriz de confusión y métricas por clase
+mod metricas;
+use metricas::MatrizConfusion;
+
+// Función objetivo a maximizar
+mod objetivo;
+use objetivo::Objetivo;
+
+// Clasificador k-NN
+mod knn;
+use knn::Knn;
+
+// Distancias entre muestras
+mod distancia;
+use distancia::Distancia;
+
+// Índice de vecinos más cercanos
+mod indice;
+
+// Núcleos vectorizados de las distancias
+mod nucleos;
+
+// Etiquetas de clase
+mod etiquetas;
+use etiquetas::CodificadorEtiquetas;
+
+// Ejecución en paralelo de los trabajos
+mod planificador;
+
 // Control de errores
 use std::error::Error;
 use std::process;
@@ -9,6 +72,10 @@ use std::process;
 // en las particiones
 use std::collections::HashMap;
 
+// Datos compartidos por los trabajos de una partición
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::{Arc, Mutex};
+
 // Medidas de tiempo
 use std::time::Instant;
 
@@ -19,15 +86,17 @@ use rand::distributions::{Distribution, Normal, Uniform};
 use rand::seq::SliceRandom; // Para poder mezclar con shuffle
 use rand::prelude::*;
 
-// Manejo argumentos (para indicar la semilla)
+// Manejo argumentos
 use std::env;
+mod cli;
+use cli::{Configuracion, Orden};
 
 // Potencias
 extern crate num;
 
 ///////////////// CONSTANTES /////////////////////////////////////
-const NUMERO_PARTICIONES: usize = 5;
 const ALPHA_F_OBJETIVO: f32 = 0.5;
+const UMBRAL_REDUCCION_PESOS: f32 = 0.2;
 const MAXIMO_EVALUACIONES_F_OBJ: usize = 15000;
 const VARIANZA_MUTACIONES: f64 = 0.3;
 const PROB_CRUCE_AGG: f32 = 0.7;
@@ -49,311 +118,382 @@ const TAM_POBLACION_GEN: usize = 30;
 const TAM_POBLACION_MEM: usize = 10;
 const PADRES_ESTACIONARIO: usize = 2;
 
-const CARACTERISTICAS_IONOSFERA: usize = 34;
-const CARACTERISTICAS_TEXTURA: usize = 40;
-const CARACTERISTICAS_COLPOSCPIA: usize = 62;
-
 ///////////////// ESTRUCTURAS DE DATOS ///////////////////////////
 
-// Interfaz para trabajar con un tipo de dato genérico
-pub trait DataElem<T> {
-    fn new() -> T;
-    fn get_num_attributes() -> usize;
-    fn get_id(&self) -> i32;
-    fn get_class(&self) -> i32;
-    fn get_attribute(&self, index: usize) -> f32;
-    fn set_id(&mut self, id: i32);
-    fn set_class(&mut self, class: i32);
-    fn set_attribute(&mut self, index: usize, attr: f32);
-}
+// Enum para indicar el tipo de cruce, blx o aritmético
 
-// Estructura de datos para almacenar las texturas
-#[derive(Copy, Clone)]
-pub struct TextureRecord {
-    id: i32,
-    attributes: [f32; CARACTERISTICAS_TEXTURA],
-    class: i32,
+#[derive(PartialEq)]
+enum VarianteCruce {
+    Blx,
+    Aritmetico,
 }
 
-// Implementación de la interfaz DataElem<T> para TextureRecord
-impl DataElem<TextureRecord> for TextureRecord {
-    fn new() -> TextureRecord {
-        TextureRecord {
-            id: -1,
-            attributes: [0.0; CARACTERISTICAS_TEXTURA],
-            class: -1,
-        }
-    }
-
-    fn get_num_attributes() -> usize {
-        return CARACTERISTICAS_TEXTURA;
-    }
-
-    fn get_id(&self) -> i32 {
-        return self.id;
-    }
-
-    fn get_class(&self) -> i32 {
-        return self.class;
-    }
-
-    fn get_attribute(&self, index: usize) -> f32 {
-        return self.attributes[index];
-    }
-
-    fn set_id(&mut self, id: i32) {
-        self.id = id;
-    }
+// Enum para indicar la variante del alg memético
+#[derive(PartialEq)]
+enum VarianteMemetico {
+    TodosCromosomas,
+    CromAleatorioP01,
+    MejoresNCromosomas,
+}
 
-    fn set_class(&mut self, class: i32) {
-        self.class = class;
-    }
+// Enum para indicar el tipo de mutación en differential evolution
+#[derive(PartialEq)]
+enum VarianteDiffEv {
+    Rand,
+    CurrentToBest,
+}
 
-    fn set_attribute(&mut self, index: usize, attr: f32) {
-        self.attributes[index] = attr;
-    }
+// Conjunto de datos preparado para ejecutar sus trabajos: lo que se
+// muestra antes de sus resultados, sus muestras y los conjuntos de
+// entrenamiento y validación de cada partición. Si el escalado se
+// ajusta en cada partición, las muestras están sin escalar y cada
+// partición las escala al ejecutar sus trabajos; si no, todas usan
+// estas. Igualmente, los valores ausentes que se imputan siguen aquí
+// como NaN y cada partición los imputa con su entrenamiento
+
+struct Experimento {
+    nombre: String,
+    clases: CodificadorEtiquetas,
+    objetivo: Objetivo,
+    // Escalado y función objetivo tal como se exportan
+    escalado: String,
+    texto_objetivo: String,
+    cabecera: String,
+    muestras: Arc<Muestras>,
+    // Escalado que se ajusta en cada partición, si es así
+    escalado_particion: Option<TipoEscalado>,
+    particiones: Vec<Particion>,
 }
 
-// Estructura de datos para almacenar las texturas
-#[derive(Copy, Clone)]
-pub struct IonosphereRecord {
-    id: i32,
-    attributes: [f32; CARACTERISTICAS_IONOSFERA],
-    class: i32,
+// Partición de una semilla y repetición, con lo que se muestra antes
+// de sus resultados. Los conjuntos de entrenamiento y validación son
+// las filas de sus muestras
+//
+// Las muestras imputadas y escaladas y los términos de los pares del
+// entrenamiento se calculan con el primer trabajo de la partición que
+// los necesita, los comparten los demás y se liberan al terminar el
+// último. Así sólo ocupan memoria los de las particiones que se están
+// ejecutando. Se guarda también lo que se ha tardado en calcularlos
+// (en milisegundos), que se reparte entre los trabajos que los usan
+
+struct Particion {
+    semilla: u64,
+    repeticion: usize,
+    particion: usize,
+    cabecera: String,
+    entrenamiento: Vec<usize>,
+    validacion: Vec<usize>,
+    // Valores de los ausentes, ajustados con el entrenamiento, si hay
+    // que imputarlos
+    imputador: Option<Imputador>,
+    // Trabajos de la partición que no han terminado
+    pendientes: AtomicUsize,
+    // Muestras imputadas y escaladas con el entrenamiento, si ya se
+    // han calculado
+    escaladas: Mutex<Option<(Arc<Muestras>, f64)>>,
+    // Sin calcular todavía, o el resultado de Diferencias::calcular
+    diferencias: Mutex<Option<(Option<Arc<Diferencias>>, f64)>>,
 }
 
-// Implementación de la interfaz DataElem<T> para IonosphereRecord
-impl DataElem<IonosphereRecord> for IonosphereRecord {
-    fn new() -> IonosphereRecord {
-        IonosphereRecord {
-            id: -1,
-            attributes: [0.0; CARACTERISTICAS_IONOSFERA],
-            class: -1,
+impl Particion {
+    // Muestras del experimento, con los ausentes imputados y escaladas
+    // con el entrenamiento si el escalado se ajusta en cada partición,
+    // y lo que se ha tardado en prepararlas
+    fn muestras(&self, experimento: &Experimento) -> (Arc<Muestras>, f64) {
+        if self.imputador.is_none() &&
+            experimento.escalado_particion.is_none() {
+            return (Arc::clone(&experimento.muestras), 0.0);
         }
-    }
-
-    fn get_num_attributes() -> usize {
-        return CARACTERISTICAS_IONOSFERA;
-    }
-
-    fn get_id(&self) -> i32 {
-        return self.id;
-    }
 
-    fn get_class(&self) -> i32 {
-        return self.class;
+        self.escaladas.lock().unwrap()
+            .get_or_insert_with(|| {
+                let tiempo_inicial = Instant::now();
+                let mut escaladas = Muestras::clone(&experimento.muestras);
+                if let Some(ref imputador) = self.imputador {
+                    imputador.aplicar(&mut escaladas);
+                }
+                if let Some(tipo) = experimento.escalado_particion {
+                    Escalador::ajustar(tipo,
+                                       escaladas.vista(&self.entrenamiento))
+                        .aplicar(&mut escaladas);
+                }
+                (Arc::new(escaladas), milisegundos(tiempo_inicial))
+            })
+            .clone()
     }
 
-    fn get_attribute(&self, index: usize) -> f32 {
-        return self.attributes[index];
+    // Términos de los pares del entrenamiento, calculados sólo la
+    // primera vez, y lo que se ha tardado en calcularlos
+    fn diferencias(&self, entrenamiento: Vista, knn: &Knn)
+                   -> (Option<Arc<Diferencias>>, f64) {
+        self.diferencias.lock().unwrap()
+            .get_or_insert_with(|| {
+                let tiempo_inicial = Instant::now();
+                let diferencias = Diferencias::calcular(entrenamiento, knn)
+                    .map(Arc::new);
+                (diferencias, milisegundos(tiempo_inicial))
+            })
+            .clone()
     }
 
-    fn set_id(&mut self, id: i32) {
-        self.id = id;
+    // Anota que ha terminado un trabajo de la partición y, si era el
+    // último, libera lo que compartían
+    fn terminar_trabajo(&self) {
+        if self.pendientes.fetch_sub(1, atomic::Ordering::AcqRel) == 1 {
+            *self.escaladas.lock().unwrap() = None;
+            *self.diferencias.lock().unwrap() = None;
+        }
     }
+}
 
-    fn set_class(&mut self, class: i32) {
-        self.class = class;
-    }
+// Ejecución de un algoritmo sobre una partición de un experimento
 
-    fn set_attribute(&mut self, index: usize, attr: f32) {
-        self.attributes[index] = attr;
-    }
+struct Trabajo {
+    experimento: usize,
+    particion: usize,
+    algoritmo: Algoritmo,
 }
 
-// Estructura de datos para almacenar las colposcopias
-#[derive(Copy, Clone)]
-pub struct ColposcopyRecord {
-    id: i32,
-    attributes: [f32; CARACTERISTICAS_COLPOSCPIA],
-    class: i32,
+// Enum con los algoritmos que se pueden ejecutar desde la línea de
+// órdenes
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Algoritmo {
+    Clasificador1nn,
+    Relief,
+    BusquedaLocal,
+    AggAritmetico,
+    AggBlx,
+    AgeAritmetico,
+    AgeBlx,
+    MemeticoTodos,
+    MemeticoAleatorio,
+    MemeticoMejores,
+    EnfriamientoSimulado,
+    Ils,
+    DiffEvRand,
+    DiffEvCurrentToBest,
 }
 
-// Implementación de la interfaz DataElem<T> para ColposcopyRecord
-impl DataElem<ColposcopyRecord> for ColposcopyRecord {
-    fn new() -> ColposcopyRecord {
-        ColposcopyRecord {
-            id: -1,
-            attributes: [0.0; CARACTERISTICAS_COLPOSCPIA],
-            class: -1,
+impl Algoritmo {
+    // En el orden en el que se muestran los resultados
+    const TODOS: [Algoritmo; 14] = [
+        Algoritmo::Clasificador1nn,
+        Algoritmo::Relief,
+        Algoritmo::BusquedaLocal,
+        Algoritmo::AggAritmetico,
+        Algoritmo::AggBlx,
+        Algoritmo::AgeAritmetico,
+        Algoritmo::AgeBlx,
+        Algoritmo::MemeticoTodos,
+        Algoritmo::MemeticoAleatorio,
+        Algoritmo::MemeticoMejores,
+        Algoritmo::EnfriamientoSimulado,
+        Algoritmo::Ils,
+        Algoritmo::DiffEvRand,
+        Algoritmo::DiffEvCurrentToBest,
+    ];
+
+    // Nombre con el que se indica en la línea de órdenes. Los que
+    // tienen variantes son familia-variante
+    fn nombre(self) -> &'static str {
+        match self {
+            Algoritmo::Clasificador1nn => "1nn",
+            Algoritmo::Relief => "relief",
+            Algoritmo::BusquedaLocal => "bl",
+            Algoritmo::AggAritmetico => "agg-ca",
+            Algoritmo::AggBlx => "agg-blx",
+            Algoritmo::AgeAritmetico => "age-ca",
+            Algoritmo::AgeBlx => "age-blx",
+            Algoritmo::MemeticoTodos => "am-todos",
+            Algoritmo::MemeticoAleatorio => "am-aleatorio",
+            Algoritmo::MemeticoMejores => "am-mejores",
+            Algoritmo::EnfriamientoSimulado => "es",
+            Algoritmo::Ils => "ils",
+            Algoritmo::DiffEvRand => "de-rand",
+            Algoritmo::DiffEvCurrentToBest => "de-ctb",
         }
     }
 
-    fn get_num_attributes() -> usize {
-        return CARACTERISTICAS_COLPOSCPIA;
-    }
-
-    fn get_id(&self) -> i32 {
-        return self.id;
-    }
-
-    fn get_class(&self) -> i32 {
-        return self.class;
-    }
-
-    fn get_attribute(&self, index: usize) -> f32 {
-        return self.attributes[index];
-    }
-
-    fn set_id(&mut self, id: i32) {
-        self.id = id;
+    // Título con el que se muestran sus resultados
+    fn titulo(self) -> &'static str {
+        match self {
+            Algoritmo::Clasificador1nn => "clasificador 1nn",
+            Algoritmo::Relief => "clasificador RELIEF",
+            Algoritmo::BusquedaLocal => "clasificador búsqueda local",
+            Algoritmo::AggAritmetico =>
+                "algoritmo genético generacional. Cruce aritmético. Elitista.",
+            Algoritmo::AggBlx =>
+                "algoritmo genético generacional. Cruce BLX. Elitista.",
+            Algoritmo::AgeAritmetico =>
+                "algoritmo genético estacionario. Cruce aritmético.",
+            Algoritmo::AgeBlx =>
+                "algoritmo genético estacionario. Cruce BLX.",
+            Algoritmo::MemeticoTodos =>
+                "algoritmo memético con BL sobre todos los cromosomas. Cruce BLX.",
+            Algoritmo::MemeticoAleatorio =>
+                "algoritmo memético con BL sobre cromosomas aleatorios con p=0.1. Cruce BLX.",
+            Algoritmo::MemeticoMejores =>
+                "algoritmo memético con BL sobre los mejores n cromosomas. Cruce BLX.",
+            Algoritmo::EnfriamientoSimulado =>
+                "algoritmo enfriamiento simulado.",
+            Algoritmo::Ils => "algoritmo ILS.",
+            Algoritmo::DiffEvRand =>
+                "algoritmo Evolución Diferencial - Rand.",
+            Algoritmo::DiffEvCurrentToBest =>
+                "algoritmo Evolución Diferencial - Current to best.",
+        }
     }
 
-    fn set_class(&mut self, class: i32) {
-        self.class = class;
+    // Si busca pesos evaluando la función objetivo
+    fn evalua_objetivo(self) -> bool {
+        !matches!(self, Algoritmo::Clasificador1nn | Algoritmo::Relief)
     }
 
-    fn set_attribute(&mut self, index: usize, attr: f32) {
-        self.attributes[index] = attr;
+    fn desde_nombre(nombre: &str) -> Option<Algoritmo> {
+        Algoritmo::TODOS.iter().cloned().find(|a| a.nombre() == nombre)
     }
 }
 
-// Enum para indicar el tipo de cruce, blx o aritmético
-
-#[derive(PartialEq)]
-enum VarianteCruce {
-    BLX,
-    ARIT,
-}
-
-// Enum para indicar la variante del alg memético
-#[derive(PartialEq)]
-enum VarianteMemetico {
-    TODOS_CROMOSOMAS,
-    CROM_ALEATORIO_P0_1,
-    MEJORES_N_CROMOSOMAS,
-}
-
-// Enum para indicar el tipo de mutación en differential evolution
-#[derive(PartialEq)]
-enum VarianteDiffEv {
-    RAND,
-    CURRENT_TO_BEST,
-}
-
 /////////////// MÉTODOS DE LOS ALGORITMOS ////////////////////
 
-// Algoritmo clasificador 1-NN (asumiendo todos los pesos igual de
-// importantes)
-//
-// Recibe el conjunto de entrenamiento y el de evaluación, clasifica
-// los de evaluación en función de la distancia a los primeros
-//
-// Devuelve una tupla con tasa de clasificación, de reducción (0.0 en
-// este caso) y función objetivo
+// Clase asignada por el 1-NN a cada miembro del conjunto de evaluación,
+// con la distancia indicada. Un miembro no es candidato de sí mismo;
+// sin ningún otro candidato se asigna la clase 0
 
-fn clasificador_1nn<T: DataElem<T> + Copy + Clone>(
-    set_entrenamiento: &Vec<T>,
-    set_evaluacion: &Vec<T>)
-    -> (f32, f32, f32) {
+fn predecir_1nn(
+    set_entrenamiento: Vista,
+    set_evaluacion: Vista,
+    distancia: Distancia)
+    -> Vec<i32> {
 
-    let mut v_clasificaciones: Vec<i32> = Vec::new(); // TODO: Tal vez
-    // esto de error con clases que no sean numéricas
+    let mut v_clasificaciones: Vec<i32> = Vec::new();
 
     for miembro in set_evaluacion.iter() {
-        let mut clase_vecino_mas_cercano =
-            set_entrenamiento[0].get_class();
-        let mut distancia_vecino_mas_cercano =
-            distancia_entre_vecinos(*miembro, set_entrenamiento[0]);
+        // (distancia, clase) del vecino más cercano hasta ahora
+        let mut mas_cercano: Option<(f32, i32)> = None;
 
         for vecino in set_entrenamiento.iter() {
-            let distancia = distancia_entre_vecinos(*miembro, *vecino);
-            if distancia < distancia_vecino_mas_cercano {
-                clase_vecino_mas_cercano = vecino.get_class();
-                distancia_vecino_mas_cercano = distancia;
+            if miembro.get_id() != vecino.get_id() { // En caso de que
+                // set_entrenamiento = set_evaluacion
+                let distancia_vecino = distancia.entre(miembro, vecino);
+                if mas_cercano.is_none_or(|(d, _)| distancia_vecino < d) {
+                    mas_cercano = Some((distancia_vecino, vecino.get_class()));
+                }
             }
         }
-        v_clasificaciones.push(clase_vecino_mas_cercano); 
+        v_clasificaciones.push(mas_cercano.map_or(0, |(_, clase)| clase));
     }
 
-    // Obtenemos la tupla resultante
-    let tasa_clas: f32 = tasa_clasificacion(&set_evaluacion,
-                                            &v_clasificaciones);
-    let tasa_red = 0.0; // Suponemos que todos los pesos ponderan con
-    // 1 y por tanto ninguno es menor que 0.2 y se reduce
-    let f_objetivo = ALPHA_F_OBJETIVO * tasa_clas +
-        (1.0 - ALPHA_F_OBJETIVO) * tasa_red;
-
-    return (tasa_clas, tasa_red, f_objetivo);
+    v_clasificaciones
 }
 
 
-// Algoritmo clasificador 1-NN (con pesos independientes)
+// Evalúa las clases asignadas por el 1-NN al conjunto de evaluación
 //
-// Recibe el conjunto de entrenamiento y el de evaluación, clasifica
-// los de evaluación en función de la distancia a los primeros
+// Recibe además la tasa de reducción de los pesos usados y la función
+// objetivo a calcular
 //
-// Devuelve una tupla con tasa de clasificación, de reducción 
-// y función objetivo
-
+// Devuelve una tupla con tasa de clasificación, de reducción
+// y función objetivo, y la matriz de confusión
+
+fn evaluar_predicciones(
+    set_evaluacion: Vista,
+    v_clasificaciones: &[i32],
+    tasa_red: f32,
+    objetivo: &Objetivo,
+    num_clases: usize)
+    -> ((f32, f32, f32), MatrizConfusion) {
+
+    let tasa_clas: f32 = tasa_clasificacion(set_evaluacion,
+                                            v_clasificaciones);
+    let matriz = MatrizConfusion::desde_predicciones(
+        set_evaluacion, v_clasificaciones, num_clases);
+    let f_objetivo = objetivo.valor(tasa_clas, tasa_red, &matriz);
+
+    ((tasa_clas, tasa_red, f_objetivo), matriz)
+}
 
-fn clasificador_1nn_con_pesos<T: DataElem<T> + Copy + Clone>(
-    set_entrenamiento: &Vec<T>,
-    set_evaluacion: &Vec<T>,
-    v_pesos: &Vec<f32>)
-    -> (f32, f32, f32) {
+// Los pesos menores que 0.2 no se tienen en cuenta al medir
+// distancias
+//
+// Devuelve los pesos con esos valores a cero y cuántos se han
+// reducido
 
-    let mut v_clasificaciones: Vec<i32> = Vec::new(); // TODO: Tal vez
-    // esto de error con clases que no sean numéricas
+fn reducir_pesos(
+    v_pesos: &[f32])
+    -> (Vec<f32>, f32) {
 
-    let mut pesos_red = v_pesos.clone();
+    let mut pesos_red = v_pesos.to_vec();
     let mut n_reducidos = 0.0;
    
     for p in pesos_red.iter_mut() {
-        if *p < 0.2 {
+        if *p < UMBRAL_REDUCCION_PESOS {
             *p = 0.0;
             n_reducidos += 1.0;
         }
     }
-    
+
+    (pesos_red, n_reducidos)
+}
+
+// Valor de un único peso al reducirlo
+
+fn peso_reducido(
+    peso: f32)
+    -> f32 {
+
+    if peso < UMBRAL_REDUCCION_PESOS { 0.0 } else { peso }
+}
+
+// Clase asignada por el 1-NN con pesos (ya reducidos) a cada miembro
+// del conjunto de evaluación, con la distancia indicada. Igual que sin
+// pesos, un miembro no es candidato de sí mismo
+
+fn predecir_1nn_con_pesos(
+    set_entrenamiento: Vista,
+    set_evaluacion: Vista,
+    pesos_red: &[f32],
+    distancia: Distancia)
+    -> Vec<i32> {
+
+    let mut v_clasificaciones: Vec<i32> = Vec::new();
+
     for miembro in set_evaluacion.iter() {
-        let mut clase_vecino_mas_cercano =
-            set_entrenamiento[0].get_class();
-        let mut distancia_vecino_mas_cercano =
-            distancia_ponderada_entre_vecinos(*miembro,
-        set_entrenamiento[0], &pesos_red);
+        // (distancia, clase) del vecino más cercano hasta ahora
+        let mut mas_cercano: Option<(f32, i32)> = None;
 
         for vecino in set_entrenamiento.iter() {
             if miembro.get_id() != vecino.get_id() { // En caso de que
                 // set_entrenamiento = set_evaluacion
-                let distancia =
-                    distancia_ponderada_entre_vecinos(*miembro, *vecino,
-                                                      &pesos_red);
-                if distancia < distancia_vecino_mas_cercano {
-                    clase_vecino_mas_cercano = vecino.get_class();
-                    distancia_vecino_mas_cercano = distancia;
+                let distancia_vecino =
+                    distancia.ponderada(miembro, vecino, pesos_red);
+                if mas_cercano.is_none_or(|(d, _)| distancia_vecino < d) {
+                    mas_cercano = Some((distancia_vecino, vecino.get_class()));
                 }
             }
         }
-        v_clasificaciones.push(clase_vecino_mas_cercano); 
+        v_clasificaciones.push(mas_cercano.map_or(0, |(_, clase)| clase));
     }
 
-    // Obtenemos la tupla resultante
-    let tasa_clas: f32 = tasa_clasificacion(&set_evaluacion,
-                                            &v_clasificaciones);
-    let tasa_red: f32 = tasa_reduccion(n_reducidos,
-                                       pesos_red.len() as f32); 
-    let f_objetivo = funcion_objetivo(tasa_clas, tasa_red);
-
-    return (tasa_clas, tasa_red, f_objetivo);
+    v_clasificaciones
 }
 
-fn tasa_clasificacion<T: DataElem<T> + Copy + Clone>(
-    set_evaluacion: &Vec<T>,
-    v_clasificaciones: &Vec<i32>)
+fn tasa_clasificacion(
+    set_evaluacion: Vista,
+    v_clasificaciones: &[i32])
     -> f32 {
     
     let mut aciertos = 0.0;
 
-    let mut counter = 0;
-    for miembro in set_evaluacion.iter(){
-        if miembro.get_class() == v_clasificaciones[counter] {
+    for (miembro, clasificacion) in set_evaluacion.iter()
+        .zip(v_clasificaciones.iter()) {
+        if miembro.get_class() == *clasificacion {
             aciertos += 1.0;
         }
-        counter += 1;
     }
     
-    return 100.0 * aciertos / (set_evaluacion.len() as f32);
+    100.0 * aciertos / (set_evaluacion.len() as f32)
 }
 
 fn tasa_reduccion(
@@ -361,116 +501,81 @@ fn tasa_reduccion(
     n_caracteristicas: f32)
     -> f32 {
 
-    return 100.0 * n_reducidos / n_caracteristicas;
-}
-
-fn funcion_objetivo(
-    tasa_clas: f32,
-    tasa_red: f32)
-    -> f32 {
-
-    return ALPHA_F_OBJETIVO * tasa_clas + (1.0 - ALPHA_F_OBJETIVO) *
-        tasa_red; 
-}
-
-// Hallamos la distancia entre dos elementos vecinos
-//
-// Recibe los dos elementos a medir
-//
-// Devuelve la distancia en float
-
-fn distancia_entre_vecinos<T: DataElem<T> + Copy + Clone>(
-    elemento1: T,
-    elemento2: T)
-    -> f32 {
-    
-    let num_attributes = T::get_num_attributes();
-    let mut distancia: f32 = 0.0;
-    for atributo in 0..num_attributes {
-        let dif  = elemento1.get_attribute(atributo) - elemento2.get_attribute(atributo); 
-        distancia += dif * dif;
-    }
-    distancia = distancia.sqrt();
-    
-    return distancia;
-}
-
-fn distancia_ponderada_entre_vecinos<T: DataElem<T> + Copy + Clone>(
-    elemento1: T,
-    elemento2: T,
-    pesos: &Vec<f32>)
-    -> f32 {
-    
-    let num_attributes = T::get_num_attributes();
-    let mut distancia: f32 = 0.0;
-    for atributo in 0..num_attributes {
-        let dif  = (elemento1.get_attribute(atributo) -
-                    elemento2.get_attribute(atributo))
-            * pesos[atributo]; 
-        distancia += dif * dif;
-    }
-    distancia = distancia.sqrt();
-    
-    return distancia;
+    100.0 * n_reducidos / n_caracteristicas
 }
 
 //////////////////////////////////////////////////
 /////////// ALGORITMOS UTILIZADOS ////////////////
 //////////////////////////////////////////////////
 
-// Algoritmo Relief (Greedy)
+// Algoritmo Relief (Greedy), buscando amigos y enemigos con la
+// distancia y el índice del clasificador
 
-fn algoritmo_relief<T: DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>)
+fn algoritmo_relief(
+    datos: Vista,
+    knn: &Knn)
     -> Vec<f32> {
     
-    let num_attributes = T::get_num_attributes();
+    let num_attributes = datos.num_atributos();
     let mut vector_pesos = vec![0.0;num_attributes];
+    let distancia = knn.distancia;
+    let arbol = knn.arbol(datos);
 
     for miembro in datos.iter() {
         // Buscamos al enemigo y al amigo más cercano
         let mut enemigo_mas_cercano_indice = 0;
         let mut amigo_mas_cercano_indice = 0;
-        let mut dist_enemigo_mas_cercano = std::f32::MAX;
-        let mut dist_amigo_mas_cercano = std::f32::MAX;
-
-        let mut counter = 0;
-        for vecino in datos.iter() {
-            if miembro.get_id() != vecino.get_id() { // Comprobamos que no estemos comparando un objeto consigo mismo
-                let distancia = distancia_entre_vecinos(*miembro, *vecino);
-                // Comprobamos si es "enemigo" y si es mejor que el actual
-                if miembro.get_class() != vecino.get_class() {
-                    if distancia < dist_enemigo_mas_cercano {
-                        dist_enemigo_mas_cercano = distancia;
-                        enemigo_mas_cercano_indice = counter;
-                    }
-                    // Si no es enemigo, es amigo. Comprobamos distancia
-                } else {
-                    if distancia < dist_amigo_mas_cercano {
-                        dist_amigo_mas_cercano = distancia;
-                        amigo_mas_cercano_indice = counter;
+
+        if let Some(ref arbol) = arbol {
+            let mas_cercano = |amigo: bool| {
+                arbol.vecinos(miembro, 1, None, |posicion| {
+                    (arbol.clase(posicion) == miembro.get_class()) == amigo
+                }).first().map_or(0, |&(_, posicion)| posicion)
+            };
+            amigo_mas_cercano_indice = mas_cercano(true);
+            enemigo_mas_cercano_indice = mas_cercano(false);
+        } else {
+            let mut dist_enemigo_mas_cercano = f32::MAX;
+            let mut dist_amigo_mas_cercano = f32::MAX;
+
+            for (counter, vecino) in datos.iter().enumerate() {
+                // Comprobamos que no estemos comparando un objeto
+                // consigo mismo
+                if miembro.get_id() != vecino.get_id() {
+                    let distancia_vecino = distancia.entre(miembro, vecino);
+                    // Comprobamos si es "enemigo" y si es mejor que el
+                    // actual
+                    if miembro.get_class() != vecino.get_class() {
+                        if distancia_vecino < dist_enemigo_mas_cercano {
+                            dist_enemigo_mas_cercano = distancia_vecino;
+                            enemigo_mas_cercano_indice = counter;
+                        }
+                        // Si no es enemigo, es amigo. Comprobamos
+                        // distancia
+                    } else {
+                        if distancia_vecino < dist_amigo_mas_cercano {
+                            dist_amigo_mas_cercano = distancia_vecino;
+                            amigo_mas_cercano_indice = counter;
+                        }
                     }
                 }
             }
-            counter += 1;
         }
         // Componente a componente trabajamos con los pesos del vector
-        // según la distancia a su mejor amigo y enemigo. Clonamos al
-        // amigo y al enemigo para hacer las cuentas
+        // según la distancia a su mejor amigo y enemigo
         let amigo_mas_cercano =
-            datos[amigo_mas_cercano_indice].clone();
+            datos.muestra(amigo_mas_cercano_indice);
         let enemigo_mas_cercano =
-            datos[enemigo_mas_cercano_indice].clone();
+            datos.muestra(enemigo_mas_cercano_indice);
 
-        for componente in 0..num_attributes {
+        for (componente, peso) in vector_pesos.iter_mut().enumerate() {
             let dist_atributo_amigo =
                 (miembro.get_attribute(componente) -
                  amigo_mas_cercano.get_attribute(componente)).abs();
             let dist_atributo_enemigo =
                 (miembro.get_attribute(componente) -
                  enemigo_mas_cercano.get_attribute(componente)).abs();
-            vector_pesos[componente] += dist_atributo_enemigo -
-                                         dist_atributo_amigo;
+            *peso += dist_atributo_enemigo - dist_atributo_amigo;
         }
     }
 
@@ -492,17 +597,17 @@ fn algoritmo_relief<T: DataElem<T> + Copy + Clone>(
         }
     }
 
-    return vector_pesos;
+    vector_pesos
 }
 
 // Búsqueda Local
 
-fn busqueda_local<T: DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
+fn busqueda_local(
+    evaluador: &Evaluador,
     seed_u64: u64,
-    sol_inicial: &Vec<f32>)
+    sol_inicial: &[f32])
     -> Vec<f32> {
-    let num_attributes = T::get_num_attributes();
+    let num_attributes = evaluador.num_atributos();
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed_u64); // Para generar números aleatorios
 
     let distribucion_uniforme = Uniform::new(0.0, 1.0);
@@ -512,10 +617,10 @@ fn busqueda_local<T: DataElem<T> + Copy + Clone>(
     let mut pesos: Vec<f32> = vec![0.0; num_attributes];
 
     if !sol_inicial.ends_with(&pesos) {
-        pesos = sol_inicial.clone();
+        pesos = sol_inicial.to_vec();
     } else {
-        for atributo in 0..num_attributes {
-            pesos[atributo] = distribucion_uniforme.sample(&mut rng);
+        for peso in pesos.iter_mut() {
+            *peso = distribucion_uniforme.sample(&mut rng);
         }
     }
 
@@ -529,16 +634,15 @@ fn busqueda_local<T: DataElem<T> + Copy + Clone>(
     let mut n_vecinos_gen_sin_mejorar = 0;
     let max_vecinos_gen_sin_mejorar = MULTIPLICADOR_MAX_V_SIN_MEJ_BL * num_attributes;
 
-    // Comprobamos la calidad de estos pesos 
-    let mut mejor_f_obj = clasificador_1nn_con_pesos(&datos, &datos,
-                                                  &pesos).2;
+    // Comprobamos la calidad de estos pesos. Cada vecino cambia un
+    // solo peso, así que se evalúan de forma incremental
+    let mut evaluacion = evaluador.incremental(&pesos);
+    let mut mejor_f_obj = evaluacion.valor().2;
 
     //println!("F obj inicial: {}", mejor_f_obj);
 
     while n_vecinos_gen_sin_mejorar < max_vecinos_gen_sin_mejorar &&
         n_mutaciones < MAXIMO_EVALUACIONES_F_OBJ {
-            let mut pesos_aux = pesos.clone();
-
             if indices.is_empty() {
                 indices = (0..num_attributes).collect();
                 indices.shuffle(&mut rng);
@@ -546,19 +650,16 @@ fn busqueda_local<T: DataElem<T> + Copy + Clone>(
 
             let indice_a_mejorar = indices.pop().expect("Vector vacío");
 
-            pesos_aux[indice_a_mejorar] +=
-                distribucion_normal.sample(&mut rng) as f32;
-            if pesos_aux[indice_a_mejorar] < 0.0 {
-                pesos_aux[indice_a_mejorar] = 0.0;
-            } else if pesos_aux[indice_a_mejorar] > 1.0 {
-                pesos_aux[indice_a_mejorar] = 1.0;
-            }
+            let mut peso_aux = pesos[indice_a_mejorar];
+            peso_aux += distribucion_normal.sample(&mut rng) as f32;
+            peso_aux = peso_aux.clamp(0.0, 1.0);
 
-            let f_obj_actual = clasificador_1nn_con_pesos(&datos,
-                                                          &datos, &pesos_aux).2;
+            let f_obj_actual =
+                evaluacion.evaluar_cambio(indice_a_mejorar, peso_aux).2;
 
             if f_obj_actual > mejor_f_obj {
-                pesos = pesos_aux;
+                evaluacion.aceptar_cambio();
+                pesos[indice_a_mejorar] = peso_aux;
                 mejor_f_obj = f_obj_actual;
                 n_vecinos_gen_sin_mejorar = 0;
 
@@ -574,18 +675,18 @@ fn busqueda_local<T: DataElem<T> + Copy + Clone>(
             n_mutaciones += 1;
         }    
     
-    return pesos;    
+    pesos
 }
 
 
 // Algoritmo genético estacionario con remplazo elitista
-fn alg_genetico_generacional_elitista<T: DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
+fn alg_genetico_generacional_elitista(
+    evaluador: &Evaluador,
     seed_u64: u64,
     variante_cruce: VarianteCruce)
     -> Vec<f32> {
     
-    let num_attributes = T::get_num_attributes();
+    let num_attributes = evaluador.num_atributos();
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed_u64); // Para generar números aleatorios con una semilla
     let mut pesos: Vec<f32> = vec![0.0; num_attributes];
 
@@ -611,32 +712,14 @@ fn alg_genetico_generacional_elitista<T: DataElem<T> + Copy + Clone>(
         // meterlos en una estructura formada por un par que contengan
         // el cromosoma en sí y su f_obj para ahorrarnos repetir la
         // evaluación en inmediatamente posteriores
-        let mut pob_evaluada: Vec<(Vec<f32>, f32)> =
-            Vec::with_capacity(TAM_POBLACION_GEN);
+        let pob_evaluada: Vec<(Vec<f32>, f32)> = poblacion.iter()
+            .cloned()
+            .zip(evaluador.evaluar_poblacion(&poblacion))
+            .collect();
         let mut mejor_cromosoma = 0;
-        pob_evaluada.push(
-            (
-                poblacion[0].clone(),
-                clasificador_1nn_con_pesos(&datos,
-                                           &datos,
-                                           &poblacion[0]).2
-            )
-        );
-        for i in 0..poblacion.len() {
-            if i != mejor_cromosoma {
-                pob_evaluada.push(
-                    (
-                        poblacion[i].clone(),
-                        clasificador_1nn_con_pesos(&datos,
-                                                   &datos,
-                                                   &poblacion[i]).2
-                    )
-                );
-
-                if pob_evaluada[i].1 > pob_evaluada[mejor_cromosoma].1
-                {
-                    mejor_cromosoma = i;
-                }        
+        for (i, cromosoma) in pob_evaluada.iter().enumerate() {
+            if cromosoma.1 > pob_evaluada[mejor_cromosoma].1 {
+                mejor_cromosoma = i;
             }
         }
         
@@ -677,16 +760,16 @@ fn alg_genetico_generacional_elitista<T: DataElem<T> + Copy + Clone>(
         let n_cruces: usize = ((tam_poblacion_padres as f32) *
                                    PROB_CRUCE_AGG).trunc() as usize;
             
-        if variante_cruce == VarianteCruce::ARIT {
-            cruce_aritmetico(&datos,
+        if variante_cruce == VarianteCruce::Aritmetico {
+            cruce_aritmetico(evaluador,
                              &seleccionados,
                              &mut pob_provisional,
                              n_cruces,
                              num_attributes);
             contador_evaluaciones += n_cruces;
             
-        } else if variante_cruce == VarianteCruce::BLX {
-            cruce_blx(&datos,
+        } else if variante_cruce == VarianteCruce::Blx {
+            cruce_blx(evaluador,
                       &seleccionados,
                       &mut pob_provisional,
                       n_cruces,
@@ -725,18 +808,12 @@ fn alg_genetico_generacional_elitista<T: DataElem<T> + Copy + Clone>(
             
             pesos_aux[gen_mut] +=
                 distribucion_normal.sample(&mut rng) as f32;
-            if pesos_aux[gen_mut] < 0.0 {
-                pesos_aux[gen_mut] = 0.0;
-            } else if pesos_aux[gen_mut] > 1.0 {
-                pesos_aux[gen_mut] = 1.0;
-            }
+            pesos_aux[gen_mut] = pesos_aux[gen_mut].clamp(0.0, 1.0);
 
             pob_provisional.remove(cromosoma_mut);
             pob_provisional.push(
                 (pesos_aux.clone(),
-                 clasificador_1nn_con_pesos(&datos,
-                                            &datos,
-                                            &pesos_aux).2
+                 evaluador.evaluar(&pesos_aux).2
                 )
             );
             
@@ -750,9 +827,7 @@ fn alg_genetico_generacional_elitista<T: DataElem<T> + Copy + Clone>(
         // Buscamos si la mejor solución está en la población
         // provisional
         let f_mejor_cromosoma_gen_anterior =
-            clasificador_1nn_con_pesos(&datos,
-                                       &datos,
-                                       &poblacion[mejor_cromosoma]).2;
+            evaluador.evaluar(&poblacion[mejor_cromosoma]).2;
         // println!("Eval: {} Mejor padre fitness: {}, ",
         //          contador_evaluaciones,
         //          f_mejor_cromosoma_gen_anterior); 
@@ -763,8 +838,8 @@ fn alg_genetico_generacional_elitista<T: DataElem<T> + Copy + Clone>(
         let mut mejor_cromosoma_actual = 0;
         let mut mejor_f = pob_provisional[0].1;
         
-        for i in 0..pob_provisional.len() {
-            let f_actual = pob_provisional[i].1;
+        for (i, cromosoma) in pob_provisional.iter().enumerate() {
+            let f_actual = cromosoma.1;
             if f_actual == f_mejor_cromosoma_gen_anterior {
                 mejor_crom_introducido = true;
             }
@@ -807,16 +882,16 @@ fn alg_genetico_generacional_elitista<T: DataElem<T> + Copy + Clone>(
     }
 
     
-    return pesos;
+    pesos
 }
 
-fn alg_genetico_estacionario<T: DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
+fn alg_genetico_estacionario(
+    evaluador: &Evaluador,
     seed_u64: u64,
     variante_cruce: VarianteCruce)
     -> Vec<f32> {
 
-    let num_attributes = T::get_num_attributes();
+    let num_attributes = evaluador.num_atributos();
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed_u64); // Para generar números aleatorios con una semilla
     let mut pesos: Vec<f32> = vec![0.0; num_attributes];
 
@@ -835,23 +910,14 @@ fn alg_genetico_estacionario<T: DataElem<T> + Copy + Clone>(
     let mut contador_evaluaciones: usize = 0;
 
     // Evaluamos esta población
-    let mut pob_evaluada: Vec<(Vec<f32>, f32)> =
-        Vec::with_capacity(TAM_POBLACION_GEN);
-    
-    for i in 0..poblacion.len() {
-        pob_evaluada.push(
-            (
-                poblacion[i].clone(),
-                clasificador_1nn_con_pesos(&datos,
-                                           &datos,
-                                           &poblacion[i]).2
-            )
-        );
-    }
+    let mut pob_evaluada: Vec<(Vec<f32>, f32)> = poblacion.iter()
+        .cloned()
+        .zip(evaluador.evaluar_poblacion(&poblacion))
+        .collect();
     
     contador_evaluaciones += TAM_POBLACION_GEN;
 
-    let mut generacion = 0;
+    let _generacion = 0;
     while contador_evaluaciones < MAXIMO_EVALUACIONES_F_OBJ {
         
         // Seleccionamos los dos padres para el estacionario
@@ -876,16 +942,20 @@ fn alg_genetico_estacionario<T: DataElem<T> + Copy + Clone>(
         // a entrar en la población de la siguiente generación
         let n_cruces: usize = 2;
         
-        if variante_cruce == VarianteCruce::ARIT {
-            cruce_aritmetico(&datos, &seleccionados, &mut pob_provisional, n_cruces, num_attributes);
+        if variante_cruce == VarianteCruce::Aritmetico {
+            cruce_aritmetico(evaluador, &seleccionados, &mut pob_provisional, n_cruces, num_attributes);
         } else {
-            cruce_blx(&datos,
+            cruce_blx(evaluador,
                       &seleccionados,
                       &mut pob_provisional,
                       n_cruces,
                       num_attributes,
                       &mut rng);
         }
+        // Los hijos se evalúan al cruzarlos. Con menos de 34 atributos
+        // no se espera ninguna mutación, así que sólo estas
+        // evaluaciones hacen avanzar el contador
+        contador_evaluaciones += n_cruces;
 
         // Mutamos el número de genes esperado
         let mutaciones_esperadas = (num_attributes as f32 *
@@ -904,18 +974,12 @@ fn alg_genetico_estacionario<T: DataElem<T> + Copy + Clone>(
             
             pesos_aux[gen_mut] +=
                 distribucion_normal.sample(&mut rng) as f32;
-            if pesos_aux[gen_mut] < 0.0 {
-                pesos_aux[gen_mut] = 0.0;
-            } else if pesos_aux[gen_mut] > 1.0 {
-                pesos_aux[gen_mut] = 1.0;
-            }
+            pesos_aux[gen_mut] = pesos_aux[gen_mut].clamp(0.0, 1.0);
 
             pob_provisional.remove(cromosoma_mut);
             pob_provisional.push(
                 (pesos_aux.clone(),
-                 clasificador_1nn_con_pesos(&datos,
-                                            &datos,
-                                            &pesos_aux).2
+                 evaluador.evaluar(&pesos_aux).2
                 )
             );            
             mut_realizadas += 1;
@@ -929,12 +993,12 @@ fn alg_genetico_estacionario<T: DataElem<T> + Copy + Clone>(
         // acabamos de introducir)
         
         //let mut mejor_f = 0.0; // DEBUG: Mostrar mejor por generacion
-        for i in 0..seleccionados.len() {
-            let mut min_f = 100.0;
+        for candidato in pob_provisional.iter().take(seleccionados.len()) {
+            let mut min_f = pob_evaluada[0].1;
             let mut min_pos = 0;
-            let mut counter = 0;
-            // Buscamos el peor cromosoma
-            for cromosoma in pob_evaluada.iter() {
+            // Buscamos el peor cromosoma (la función objetivo puede tomar
+            // cualquier valor, así que se parte del primero)
+            for (counter, cromosoma) in pob_evaluada.iter().enumerate() {
                 if cromosoma.1 < min_f {
                     min_f = cromosoma.1;
                     min_pos = counter;
@@ -944,18 +1008,17 @@ fn alg_genetico_estacionario<T: DataElem<T> + Copy + Clone>(
                 // if cromosoma.1 > mejor_f {
                 //     mejor_f = cromosoma.1;
                 // }
-                 counter += 1;
             }
 
             // Si es peor que uno de los candidatos, lo expulsamos
-            if min_f < pob_provisional[i].1 {
+            if min_f < candidato.1 {
                 poblacion.remove(min_pos);
-                poblacion.push(pob_provisional[i].0.clone());
+                poblacion.push(candidato.0.clone());
                 pob_evaluada.remove(min_pos);
                 pob_evaluada.push(
                     (
-                        pob_provisional[i].0.clone(),
-                        pob_provisional[i].1)
+                        candidato.0.clone(),
+                        candidato.1)
                 );
             }
         }
@@ -972,50 +1035,32 @@ fn alg_genetico_estacionario<T: DataElem<T> + Copy + Clone>(
 
     // Ahora buscamos el mejor cromosoma en la población final para
     // devolverlo como solución
+    let pob_evaluada: Vec<(Vec<f32>, f32)> = poblacion.iter()
+        .cloned()
+        .zip(evaluador.evaluar_poblacion(&poblacion))
+        .collect();
     let mut mejor_cromosoma = 0;
-    let mut pob_evaluada: Vec<(Vec<f32>, f32)> =
-        Vec::with_capacity(TAM_POBLACION_GEN); 
-        pob_evaluada.push(
-            (
-                poblacion[0].clone(),
-                clasificador_1nn_con_pesos(&datos,
-                                           &datos,
-                                           &poblacion[0]).2
-            )
-        );
-        for i in 0..poblacion.len() {
-            if i != mejor_cromosoma {
-                pob_evaluada.push(
-                    (
-                        poblacion[i].clone(),
-                        clasificador_1nn_con_pesos(&datos,
-                                                   &datos,
-                                                   &poblacion[i]).2
-                    )
-                );
-
-                if pob_evaluada[i].1 > pob_evaluada[mejor_cromosoma].1
-                {
-                    mejor_cromosoma = i;
-                }        
-            }
+    for (i, cromosoma) in pob_evaluada.iter().enumerate() {
+        if cromosoma.1 > pob_evaluada[mejor_cromosoma].1 {
+            mejor_cromosoma = i;
         }
+    }
 
     pesos = pob_evaluada[mejor_cromosoma].0.clone();
     
-    return pesos;
+    pesos
 }
 
 
 // El algoritmo memético a desarrollar utilizará una búsqueda local
 // sobre el algoritmo genético estacionario con cruce BLX, que ha sido
 // el que mejor resultados nos ha dado 
-fn alg_memetico<T: DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
+fn alg_memetico(
+    evaluador: &Evaluador,
     seed_u64: u64,
     variante_memetico: VarianteMemetico)
     -> Vec<f32> {
-    let num_attributes = T::get_num_attributes();
+    let num_attributes = evaluador.num_atributos();
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed_u64); // Para generar números aleatorios con una semilla
     let mut pesos: Vec<f32> = vec![0.0; num_attributes];
 
@@ -1035,32 +1080,14 @@ fn alg_memetico<T: DataElem<T> + Copy + Clone>(
     //    let mut generacion = 0; // DEBUG: Mejor por generación
     while contador_evaluaciones < MAXIMO_EVALUACIONES_F_OBJ {
         // Algoritmo genético generacional elitista con cruce BLX
-        let mut pob_evaluada: Vec<(Vec<f32>, f32)> =
-            Vec::with_capacity(TAM_POBLACION_MEM);
+        let mut pob_evaluada: Vec<(Vec<f32>, f32)> = poblacion.iter()
+            .cloned()
+            .zip(evaluador.evaluar_poblacion(&poblacion))
+            .collect();
         let mut mejor_cromosoma = 0;
-        pob_evaluada.push(
-            (
-                poblacion[0].clone(),
-                clasificador_1nn_con_pesos(&datos,
-                                           &datos,
-                                           &poblacion[0]).2
-            )
-        );
-        for i in 0..poblacion.len() {
-            if i != mejor_cromosoma {
-                pob_evaluada.push(
-                    (
-                        poblacion[i].clone(),
-                        clasificador_1nn_con_pesos(&datos,
-                                                   &datos,
-                                                   &poblacion[i]).2
-                    )
-                );
-
-                if pob_evaluada[i].1 > pob_evaluada[mejor_cromosoma].1
-                {
-                    mejor_cromosoma = i;
-                }        
+        for (i, cromosoma) in pob_evaluada.iter().enumerate() {
+            if cromosoma.1 > pob_evaluada[mejor_cromosoma].1 {
+                mejor_cromosoma = i;
             }
         }
         
@@ -1090,7 +1117,7 @@ fn alg_memetico<T: DataElem<T> + Copy + Clone>(
         let n_cruces: usize = ((tam_poblacion_padres as f32) *
                                    PROB_CRUCE_AGG).trunc() as usize;
             
-        cruce_blx(&datos,
+        cruce_blx(evaluador,
                   &seleccionados,
                   &mut pob_provisional,
                   n_cruces,
@@ -1131,18 +1158,12 @@ fn alg_memetico<T: DataElem<T> + Copy + Clone>(
             
             pesos_aux[gen_mut] +=
                 distribucion_normal.sample(&mut rng) as f32;
-            if pesos_aux[gen_mut] < 0.0 {
-                pesos_aux[gen_mut] = 0.0;
-            } else if pesos_aux[gen_mut] > 1.0 {
-                pesos_aux[gen_mut] = 1.0;
-            }
+            pesos_aux[gen_mut] = pesos_aux[gen_mut].clamp(0.0, 1.0);
 
             pob_provisional.remove(cromosoma_mut);
             pob_provisional.push(
                 (pesos_aux.clone(),
-                 clasificador_1nn_con_pesos(&datos,
-                                            &datos,
-                                            &pesos_aux).2
+                 evaluador.evaluar(&pesos_aux).2
                 )
             );
             mut_realizadas += 1;
@@ -1158,33 +1179,28 @@ fn alg_memetico<T: DataElem<T> + Copy + Clone>(
             //println!("Lanzamos búsqueda local. Evaluaciones: {}", contador_evaluaciones);
             contador_busqueda_local = 0;
 
-            if variante_memetico == VarianteMemetico::TODOS_CROMOSOMAS {
+            if variante_memetico == VarianteMemetico::TodosCromosomas {
                 // Lanzamos búsqueda local sobre todos los individuos
                 for i in 0..poblacion.len() {
                    // println!("Candidato a mejorar {}",
                    //          pob_evaluada[i].1); 
                     let mut cont_eval_bl = 0;
                     let evaluaciones_bl = 2 * num_attributes;
+                    let mut evaluacion =
+                        evaluador.incremental(&pob_evaluada[i].0);
                     while cont_eval_bl < evaluaciones_bl {
-                        pob_evaluada[i].0 =
-                            aux_busqueda_local(
-                                &datos,
-                                &mut rng,
-                                &pob_evaluada[i].0,
-                                pob_evaluada[i].1,
-                                &distribucion_normal).clone();
-                        pob_evaluada[i].1 =
-                            clasificador_1nn_con_pesos(
-                                &datos,
-                                &datos,
-                                &pob_evaluada[i].0).2; 
+                        aux_busqueda_local(&mut evaluacion,
+                                           &mut rng,
+                                           &distribucion_normal);
                         contador_evaluaciones += 2; // Evaluamos
                         // dentro de la búsqueda local y ahora también
                         cont_eval_bl += 1;
                     }
+                    pob_evaluada[i] = (evaluacion.pesos().to_vec(),
+                                       evaluacion.valor().2);
                     poblacion[i] = pob_evaluada[i].0.clone();
                 }
-            } else if variante_memetico == VarianteMemetico::CROM_ALEATORIO_P0_1 {
+            } else if variante_memetico == VarianteMemetico::CromAleatorioP01 {
                 let cromosomas_a_mutar = ((poblacion.len() as f32) *
                                          PROB_BUSQUEDA_MEMETICO).trunc() as usize;
                 for _i in 0..cromosomas_a_mutar {
@@ -1192,23 +1208,18 @@ fn alg_memetico<T: DataElem<T> + Copy + Clone>(
                         rng.gen_range(0, poblacion.len());
                     let mut cont_eval_bl = 0;
                     let evaluaciones_bl = 2 * num_attributes;
+                    let mut evaluacion = evaluador.incremental(
+                        &pob_evaluada[indice_cromosoma].0);
                     while cont_eval_bl < evaluaciones_bl {
-                        pob_evaluada[indice_cromosoma].0 =
-                            aux_busqueda_local(
-                                &datos,
-                                &mut rng,
-                                &pob_evaluada[indice_cromosoma].0,
-                                pob_evaluada[indice_cromosoma].1,
-                                &distribucion_normal).clone();
-                        pob_evaluada[indice_cromosoma].1 =
-                            clasificador_1nn_con_pesos(
-                                &datos,
-                                &datos,
-                                &pob_evaluada[indice_cromosoma].0).2; 
+                        aux_busqueda_local(&mut evaluacion,
+                                           &mut rng,
+                                           &distribucion_normal);
                         contador_evaluaciones += 2; // Evaluamos
                         // dentro de la búsqueda local y ahora también
                         cont_eval_bl += 1;
                     }
+                    pob_evaluada[indice_cromosoma] =
+                        (evaluacion.pesos().to_vec(), evaluacion.valor().2);
                     poblacion[indice_cromosoma] = pob_evaluada[indice_cromosoma].0.clone();
                 }
             } else {
@@ -1216,23 +1227,18 @@ fn alg_memetico<T: DataElem<T> + Copy + Clone>(
                 let indice_cromosoma = mejor_cromosoma;
                 let mut cont_eval_bl = 0;
                 let evaluaciones_bl = 2 * num_attributes;
+                let mut evaluacion = evaluador.incremental(
+                    &pob_evaluada[indice_cromosoma].0);
                 while cont_eval_bl < evaluaciones_bl {
-                    pob_evaluada[indice_cromosoma].0 =
-                        aux_busqueda_local(
-                            &datos,
-                            &mut rng,
-                            &pob_evaluada[indice_cromosoma].0,
-                            pob_evaluada[indice_cromosoma].1,
-                            &distribucion_normal).clone();
-                    pob_evaluada[indice_cromosoma].1 =
-                        clasificador_1nn_con_pesos(
-                            &datos,
-                            &datos,
-                            &pob_evaluada[indice_cromosoma].0).2; 
+                    aux_busqueda_local(&mut evaluacion,
+                                       &mut rng,
+                                       &distribucion_normal);
                     contador_evaluaciones += 2; // Evaluamos dentro de la búsqueda local y ahora también
                                                 // Podríamos mejorar esto calculando solo una vez el f_obj
                     cont_eval_bl += 1;
                 }
+                pob_evaluada[indice_cromosoma] =
+                    (evaluacion.pesos().to_vec(), evaluacion.valor().2);
                 poblacion[indice_cromosoma] = pob_evaluada[indice_cromosoma].0.clone();
             }
             //            println!("Finaliza BL");
@@ -1243,9 +1249,7 @@ fn alg_memetico<T: DataElem<T> + Copy + Clone>(
         // Buscamos si la mejor solución está en la población
         // provisional
         let f_mejor_cromosoma_gen_anterior =
-            clasificador_1nn_con_pesos(&datos,
-                                       &datos,
-                                       &poblacion[mejor_cromosoma]).2;
+            evaluador.evaluar(&poblacion[mejor_cromosoma]).2;
 
         let mut mejor_crom_introducido = false;
         let mut peor_cromosoma = 0;
@@ -1253,8 +1257,8 @@ fn alg_memetico<T: DataElem<T> + Copy + Clone>(
         let mut mejor_cromosoma_actual = 0;
         let mut mejor_f = pob_provisional[0].1;
         
-        for i in 0..pob_provisional.len() {
-            let f_actual = pob_provisional[i].1;
+        for (i, cromosoma) in pob_provisional.iter().enumerate() {
+            let f_actual = cromosoma.1;
             if f_actual == f_mejor_cromosoma_gen_anterior {
                 mejor_crom_introducido = true;
             }
@@ -1295,16 +1299,16 @@ fn alg_memetico<T: DataElem<T> + Copy + Clone>(
             poblacion.push(cromosoma.0.clone());
         }
     }
-    return pesos;
+    pesos
 }
 
 //////////// Algoritmos práctica 3 ///////////////
 
-fn alg_enfriamiento_simulado<T:DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
+fn alg_enfriamiento_simulado(
+    evaluador: &Evaluador,
     seed_u64: u64)
     -> Vec<f32> {
-    let num_attributes = T::get_num_attributes();
+    let num_attributes = evaluador.num_atributos();
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed_u64); // Para generar números aleatorios con una semilla
 
     // Distribuciones uniforme (inicialización y comprobación de temperatura) y normal (explorador de vecindario)
@@ -1313,25 +1317,25 @@ fn alg_enfriamiento_simulado<T:DataElem<T> + Copy + Clone>(
     
     // Generamos el vector aleatorio inicial
     let mut pesos: Vec<f32> = vec![0.0; num_attributes];
-    for atributo in 0..num_attributes {
-        pesos[atributo] = distribucion_uniforme.sample(&mut rng);
+    for peso in pesos.iter_mut() {
+        *peso = distribucion_uniforme.sample(&mut rng);
     }
 
-    let mut mejor_solucion = pesos.clone();
-
-    // Calculamos temperatura inicial
-    let mut coste = clasificador_1nn_con_pesos(
-        &datos,
-        &datos,
-        &pesos).2;
+    // Calculamos temperatura inicial. Cada vecino cambia un solo peso,
+    // así que se evalúan de forma incremental
+    let mut evaluacion = evaluador.incremental(&pesos);
+    let mut coste = evaluacion.valor().2;
     let ln_valor = -(MU_PHI_TEMP).ln();
 
-    let mut temperatura = MU_PHI_TEMP * coste / ln_valor;
+    // La función objetivo puede ser negativa o nula, así que la
+    // temperatura depende de su valor absoluto (o de 1 si es nula)
+    let escala_coste = if coste != 0.0 { coste.abs() } else { 1.0 };
+    let mut temperatura = MU_PHI_TEMP * escala_coste / ln_valor;
 
     let max_vecinos = COEF_VECINOS_TEMP * num_attributes;
     let max_exitos = (COEF_EXITOS_TEMP * max_vecinos as f32) as usize;
     let m = MAXIMO_EVALUACIONES_F_OBJ as f32 / max_vecinos as f32;
-    let beta = (temperatura - TEMPERATURA_FINAL)/(m as f32 * TEMPERATURA_FINAL * temperatura);
+    let beta = (temperatura - TEMPERATURA_FINAL)/(m * TEMPERATURA_FINAL * temperatura);
 
     // Bucle externo
     let mut it = 0;
@@ -1343,19 +1347,12 @@ fn alg_enfriamiento_simulado<T:DataElem<T> + Copy + Clone>(
         while it < MAXIMO_EVALUACIONES_F_OBJ && vecinos_generados < max_vecinos && exitos < max_exitos {
 
             // Aplicamos el generador de vecindario para obtener una solución candidata
-            let mut pesos_aux = pesos.clone();
             let indice_a_mutar = rng.gen_range(0, num_attributes);
-            pesos_aux[indice_a_mutar] +=
-                distribucion_normal.sample(&mut rng) as f32;
-            if pesos_aux[indice_a_mutar] < 0.0 {
-                pesos_aux[indice_a_mutar] = 0.0;
-            } else if pesos_aux[indice_a_mutar] > 1.0 {
-                pesos_aux[indice_a_mutar] = 1.0;
-            }
-            let coste_aux = clasificador_1nn_con_pesos(
-                &datos,
-                &datos,
-                &pesos_aux).2;
+            let mut peso_aux = pesos[indice_a_mutar];
+            peso_aux += distribucion_normal.sample(&mut rng) as f32;
+            peso_aux = peso_aux.clamp(0.0, 1.0);
+            let coste_aux =
+                evaluacion.evaluar_cambio(indice_a_mutar, peso_aux).2;
            
             // Aceptación de solución
             let dif_coste = coste - coste_aux;
@@ -1364,11 +1361,9 @@ fn alg_enfriamiento_simulado<T:DataElem<T> + Copy + Clone>(
             
             if dif_coste < 0.0 || (prob_acept <= exp_value && dif_coste != 0.0) {
                 exitos += 1;
-                pesos = pesos_aux.clone();
+                evaluacion.aceptar_cambio();
+                pesos[indice_a_mutar] = peso_aux;
                 coste = coste_aux;
-                if dif_coste < 0.0 {
-                    mejor_solucion = pesos.clone();
-                }
             }
             
             // Control
@@ -1382,15 +1377,15 @@ fn alg_enfriamiento_simulado<T:DataElem<T> + Copy + Clone>(
         temperatura = temperatura / (1.0 + beta * temperatura);
     }
 
-    return pesos;
+    pesos
 }
 
-fn alg_ils<T:DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
+fn alg_ils(
+    evaluador: &Evaluador,
     seed_u64: u64)
     -> Vec<f32> {
 
-    let num_attributes = T::get_num_attributes();
+    let num_attributes = evaluador.num_atributos();
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed_u64); // Para generar números aleatorios con una semilla
 
     // Distribuciones uniforme (inicialización y comprobación de temperatura) y normal (explorador de vecindario)
@@ -1398,40 +1393,31 @@ fn alg_ils<T:DataElem<T> + Copy + Clone>(
     
     // Generamos el vector aleatorio inicial
     let mut solucion_inicial: Vec<f32> = vec![0.0; num_attributes];
-    for atributo in 0..num_attributes {
-        solucion_inicial[atributo] = distribucion_uniforme.sample(&mut rng);
+    for peso in solucion_inicial.iter_mut() {
+        *peso = distribucion_uniforme.sample(&mut rng);
     }
 
     let mut it = 0;
 
-    let f_ini = clasificador_1nn_con_pesos(
-        &datos,
-        &datos,
-        &solucion_inicial).2;
-    let mut solucion = busqueda_local(
-        &datos,
-        seed_u64,
+    let _f_ini = evaluador.evaluar(&solucion_inicial).2;
+    // Cada búsqueda local usa su propia semilla, derivada de la del
+    // ILS y del número de iteración
+    let mut solucion = busqueda_local(evaluador,
+        derivar_semilla(seed_u64, &[it as u64]),
         &solucion_inicial);
     it += 1;
     let mut mej_sol = solucion.clone();
 
-    let mut f_mej_sol = clasificador_1nn_con_pesos(
-        &datos,
-        &datos,
-        &solucion).2;
+    let mut f_mej_sol = evaluador.evaluar(&solucion).2;
     while it < ITERACIONES_ILS {
         solucion = mutacion_ils(solucion, &mut rng, num_attributes);
 
-        solucion = busqueda_local(
-            &datos,
-            seed_u64,
+        solucion = busqueda_local(evaluador,
+            derivar_semilla(seed_u64, &[it as u64]),
             &solucion);
         
 
-        let f_obj_actual = clasificador_1nn_con_pesos(
-            &datos,
-            &datos,
-            &solucion).2;
+        let f_obj_actual = evaluador.evaluar(&solucion).2;
         
         if f_obj_actual > f_mej_sol {
             f_mej_sol = f_obj_actual;
@@ -1439,16 +1425,16 @@ fn alg_ils<T:DataElem<T> + Copy + Clone>(
         }
         it +=1;
     }
-    return mej_sol;
+    mej_sol
 }
 
-fn alg_diff_evol<T:DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
+fn alg_diff_evol(
+    evaluador: &Evaluador,
     seed_u64: u64,
     variante_mutacion: VarianteDiffEv)
     -> Vec<f32> {
 
-    let num_attributes = T::get_num_attributes();
+    let num_attributes = evaluador.num_atributos();
     let mut rng: StdRng = SeedableRng::seed_from_u64(seed_u64); // Para generar números aleatorios con una semilla
     let mut pesos: Vec<f32> = vec![0.0; num_attributes];
     let mut contador_evaluaciones = 0;
@@ -1462,28 +1448,16 @@ fn alg_diff_evol<T:DataElem<T> + Copy + Clone>(
                           TAM_POBLACION_DIFF_EV);
 
     // Evaluamos esta población
-    let mut pob_evaluada: Vec<(Vec<f32>, f32)> =
-        Vec::with_capacity(TAM_POBLACION_DIFF_EV);
-    
-    for i in 0..TAM_POBLACION_DIFF_EV {
-        pob_evaluada.push(
-            (
-                poblacion[i].clone(),
-                clasificador_1nn_con_pesos(&datos,
-                                           &datos,
-                                           &poblacion[i]).2
-            )
-        );
-    }
+    let mut pob_evaluada: Vec<(Vec<f32>, f32)> = poblacion.iter()
+        .cloned()
+        .zip(evaluador.evaluar_poblacion(&poblacion))
+        .collect();
     
     contador_evaluaciones += TAM_POBLACION_DIFF_EV;
-    let mut generacion = 0;
     //    println!("Generación\tFitness");
     while contador_evaluaciones < MAXIMO_EVALUACIONES_F_OBJ {
-        generacion += 1;
-
         //DEBUG
-        let mut f_mej_sol = 0.0;
+        let mut f_mej_sol = pob_evaluada[0].1;
         for elem in pob_evaluada.iter() {
             if elem.1 > f_mej_sol {
                 f_mej_sol = elem.1;
@@ -1498,16 +1472,14 @@ fn alg_diff_evol<T:DataElem<T> + Copy + Clone>(
             let mut vector_mutado: Vec<f32> = vec![0.0; num_attributes];
 
             let mut i_mej_sol = 0;
-            if variante_mutacion == VarianteDiffEv::CURRENT_TO_BEST {
+            if variante_mutacion == VarianteDiffEv::CurrentToBest {
                 // Calculamos el mejor vector
-                let mut f_mej_sol = 0.0;
-                let mut counter = 0;
-                for elem in pob_evaluada.iter() {
+                let mut f_mej_sol = pob_evaluada[0].1;
+                for (counter, elem) in pob_evaluada.iter().enumerate() {
                     if elem.1 > f_mej_sol {
                         f_mej_sol = elem.1;
                         i_mej_sol = counter;
                     }
-                    counter += 1;
                 }
             }
             
@@ -1518,39 +1490,35 @@ fn alg_diff_evol<T:DataElem<T> + Copy + Clone>(
             let r1 = indices.pop().expect("Vector vacío");
             let r2 = indices.pop().expect("Vector vacío");
             let mut r3 = 0;
-            if variante_mutacion == VarianteDiffEv::RAND {
+            if variante_mutacion == VarianteDiffEv::Rand {
                 r3 = indices.pop().expect("Vector vacío");
             }
             
             // Para evitar generar muchos números aleatorios calculamos el número medio de cruces esperado, mezclamos un vector con los índices de los genes y extraemos los correspondientes primeros
             let mut gen_index: Vec<usize> = (0..num_attributes).collect();
             gen_index.shuffle(&mut rng);
-            for contador_mut in 0..cruces_esperados {
+            for _contador_mut in 0..cruces_esperados {
                 let i_cruce = gen_index.pop().expect("Vector vacío");
 
                 let mut nuevo_valor = 0.0;
 
-                if variante_mutacion == VarianteDiffEv::RAND {
+                if variante_mutacion == VarianteDiffEv::Rand {
                     nuevo_valor = pob_evaluada[r1].0[i_cruce] + COEF_F_DIFF_EV * (pob_evaluada[r2].0[i_cruce] - pob_evaluada[r3].0[i_cruce]);
-                } else if variante_mutacion == VarianteDiffEv::CURRENT_TO_BEST {
+                } else if variante_mutacion == VarianteDiffEv::CurrentToBest {
                     nuevo_valor = pob_evaluada[i].0[i_cruce] +
                         COEF_F_DIFF_EV * (pob_evaluada[i_mej_sol].0[i_cruce] - pob_evaluada[i].0[i_cruce]) +
                         COEF_F_DIFF_EV * (pob_evaluada[r1].0[i_cruce] - pob_evaluada[r2].0[i_cruce]);
                 }
-                if nuevo_valor < 0.0 {
-                    nuevo_valor = 0.0;
-                } else if nuevo_valor > 1.0 {
-                    nuevo_valor = 1.0;
-                }
+                nuevo_valor = nuevo_valor.clamp(0.0, 1.0);
                 vector_mutado[i_cruce] = nuevo_valor;
             }
             
-            while gen_index.len() > 0 {
-                let indice_copia = gen_index.pop().expect("Vector vacío");
+            while let Some(indice_copia) = gen_index.pop() {
+                
                 vector_mutado[indice_copia] = pob_evaluada[i].0[indice_copia];
             }
 
-            let f_mutado = clasificador_1nn_con_pesos(&datos, &datos, &vector_mutado).2;
+            let f_mutado = evaluador.evaluar(&vector_mutado).2;
             contador_evaluaciones += 1;
             
             if f_mutado > pob_evaluada[i].1 {
@@ -1560,7 +1528,7 @@ fn alg_diff_evol<T:DataElem<T> + Copy + Clone>(
         }
     }
 
-    let mut f_mej_sol = 0.0;
+    let mut f_mej_sol = pob_evaluada[0].1;
     pesos = pob_evaluada[0].0.clone();
     for elem in pob_evaluada.iter() {
         if elem.1 > f_mej_sol {
@@ -1569,7 +1537,7 @@ fn alg_diff_evol<T:DataElem<T> + Copy + Clone>(
         }
     }
 
-    return pesos;
+    pesos
 }
 
 //////////////////////////////////////////////////
@@ -1577,63 +1545,64 @@ fn alg_diff_evol<T:DataElem<T> + Copy + Clone>(
 //////////////////////////////////////////////////
 
 // Crea particiones con distribución de clases uniforme
+//
+// Las muestras de cada clase se reparten por turnos entre las
+// particiones, y cada clase sigue el turno donde lo dejó la anterior.
+// Así los tamaños de las particiones difieren como mucho en uno y, si
+// hay al menos tantas muestras como particiones, ninguna queda vacía.
+// Si se indica un generador, antes de repartirlas se
+// mezclan las de cada clase; si no, se reparten en el orden del
+// fichero. Dentro de cada partición las muestras conservan el orden
+// del fichero
+//
+// Devuelve las filas de las muestras de cada partición
 
-fn crear_particiones<T: DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>)
-    -> Vec<Vec<T>> {
-    
-    let mut particiones: Vec<Vec<T>> = Vec::new();
-    let mut diccionario_contador_clases = HashMap::new();
+fn crear_particiones(
+    datos: &Muestras,
+    num_particiones: usize,
+    rng: Option<&mut StdRng>)
+    -> Vec<Vec<usize>> {
     
-    for _i in 0..NUMERO_PARTICIONES{
-        particiones.push(Vec::new());
+    // Usamos un diccionario para agrupar los índices de las muestras
+    // de cada clase
+    let mut diccionario_indices_clases: HashMap<i32, Vec<usize>> =
+        HashMap::new();
+    let mut orden_clases: Vec<i32> = Vec::new();
+
+    for indice in 0..datos.len() {
+        let muestra = datos.muestra(indice);
+        let indices = diccionario_indices_clases.entry(muestra.get_class())
+            .or_insert_with(|| {
+                orden_clases.push(muestra.get_class());
+                Vec::new()
+            });
+        indices.push(indice);
     }
 
-    // Usamos un diccionario para contabilizar las clases y procurar
-    // una distribución uniforme de estas en todas las particiones
-    for muestra in datos.iter() {
-        let counter =
-            diccionario_contador_clases.entry(muestra.get_class()).or_insert(0);
-        particiones[*counter].push(muestra.clone());
-        *counter = (*counter + 1) % NUMERO_PARTICIONES;
+    if let Some(rng) = rng {
+        for clase in orden_clases.iter() {
+            diccionario_indices_clases.get_mut(clase).unwrap().shuffle(rng);
+        }
     }
-    
-    return particiones;
-}
 
-
-// Normalizamos los datos de entrada
-
-fn normalizar_datos<T: DataElem<T> + Copy + Clone>(
-    datos: &mut Vec<T>) {
-    
-    // Calculamos el máximo y el mínimo para cada atributo  y lo
-    // almacenamos en un vector de máximos/mínimos
-    let num_attributes = T::get_num_attributes();
-    let mut maximos = vec![std::f32::MIN; num_attributes];
-    let mut minimos = vec![std::f32::MAX; num_attributes];
-
-    for miembro in datos.iter() {
-        for atributo in 0..num_attributes {
-            let valor_actual = miembro.get_attribute(atributo);
-            if valor_actual < minimos[atributo] {
-                minimos[atributo] = valor_actual;
-            }
-            if valor_actual > maximos[atributo] {
-                maximos[atributo] = valor_actual;
-            }
+    let mut particion_de_muestra = vec![0; datos.len()];
+    let mut turno = 0;
+    for clase in orden_clases.iter() {
+        for &indice in diccionario_indices_clases[clase].iter() {
+            particion_de_muestra[indice] = turno;
+            turno = (turno + 1) % num_particiones;
         }
     }
 
-    // Una vez tenemos los máximos/mínimos normalizamos cada atributo
-    for miembro in datos.iter_mut() {
-        for atributo in 0..num_attributes {
-            if maximos[atributo] - minimos[atributo] != 0.0 {
-                miembro.set_attribute(atributo, (miembro.get_attribute(atributo) - minimos[atributo]) / (maximos[atributo] - minimos[atributo]));
-            }
-        }
+    let mut particiones: Vec<Vec<usize>> = vec![Vec::new(); num_particiones];
+    for (indice, &particion) in particion_de_muestra.iter().enumerate() {
+        particiones[particion].push(indice);
     }
+    
+    particiones
 }
+
+
 //////////////////////////////////////////////////
 ///// Procedimientos generales genéticos /////////
 //////////////////////////////////////////////////
@@ -1656,22 +1625,23 @@ let distribucion_uniforme = Uniform::new(0.0, 1.0);
     }
 }
 
-fn cruce_aritmetico<T: DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
-    seleccionados: & Vec<(Vec<f32>, f32)>,
+fn cruce_aritmetico(
+    evaluador: &Evaluador,
+    seleccionados: &[(Vec<f32>, f32)],
     pob_provisional: &mut Vec<(Vec<f32>, f32)>,
     n_cruces: usize,
     num_attributes: usize) {
     
+    let mut hijos: Vec<Vec<f32>> = Vec::with_capacity(n_cruces);
     let mut i = 0;
     while i < n_cruces {
         let mut cromosoma = Vec::with_capacity(num_attributes);
         for j in 0..num_attributes {
-            let gen;
+            
             let index = if seleccionados.len() > 2 {i} else {0};
             // Distinguimos estacionario
             // de generacional cuando hay más de dos padres 
-            gen = (seleccionados[index].0[j] +
+            let gen = (seleccionados[index].0[j] +
                    seleccionados[index+1].0[j]) / 2.0;
             
             cromosoma.push(gen);
@@ -1683,44 +1653,40 @@ fn cruce_aritmetico<T: DataElem<T> + Copy + Clone>(
         //
         // Además el índice se incrementa en 2 porque se reutiliza
         // para indexar los padres a cruzar
+        hijos.push(cromosoma);
         if seleccionados.len() > 2 {
-            pob_provisional.push(
-                (cromosoma.clone(),
-                 clasificador_1nn_con_pesos(&datos,
-                                            &datos,
-                                            &cromosoma).2
-                )
-            );
-            
             i += 2;
         } else {
-            pob_provisional.push(
-                (cromosoma.clone(),
-                 0.0
-                )
-            );
             i += 1;
         }
     }
+
+    // Los hijos se evalúan todos a la vez
+    if seleccionados.len() > 2 {
+        let valores = evaluador.evaluar_poblacion(&hijos);
+        pob_provisional.extend(hijos.into_iter().zip(valores));
+    } else {
+        pob_provisional.extend(hijos.into_iter().map(|hijo| (hijo, 0.0)));
+    }
 }
 
 
-fn cruce_blx<T: DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
-    seleccionados: & Vec<(Vec<f32>, f32)>,
+fn cruce_blx(
+    evaluador: &Evaluador,
+    seleccionados: &[(Vec<f32>, f32)],
     pob_provisional: &mut Vec<(Vec<f32>, f32)>,
     n_cruces: usize,
     num_attributes: usize,
-    mut rng: &mut StdRng) {
+    rng: &mut StdRng) {
     
+    let mut hijos: Vec<Vec<f32>> = Vec::with_capacity(n_cruces);
     let mut i = 0;
     while i < n_cruces {
         let mut cromosoma =
             Vec::with_capacity(num_attributes);
         
-        let index = if seleccionados.len() > 2 {i} else
-        {0}; // Así distinguimos  entre AGE y AGG para acceder
-        // a los padres
+        let index = if seleccionados.len() > 2 {i} else {0}; // Así
+        // distinguimos entre AGE y AGG para acceder a los padres
         
         for j in 0..num_attributes {
             let cmax;
@@ -1736,29 +1702,18 @@ fn cruce_blx<T: DataElem<T> + Copy + Clone>(
             } 
             let interval = (cmax - cmin) * BLX_VALUE as f32;
 
-            let mut gen;
-            if cmax != cmin {
-                gen = rng.gen_range(cmin - interval, cmax +
-                                    interval);
-                if gen < 0.0 {
-                    gen = 0.0;
-                } else if gen > 1.0 {
-                    gen = 0.0;
-                }
-                
+            let gen = if cmax != cmin {
+                let gen = rng.gen_range(cmin - interval, cmax + interval);
+                // Los genes fuera de [0, 1] se anulan, como en la
+                // versión original
+                if !(0.0..=1.0).contains(&gen) { 0.0 } else { gen }
             } else {
-                gen = seleccionados[index].0[j];
-            }
+                seleccionados[index].0[j]
+            };
             cromosoma.push(gen);
         }
 
-        pob_provisional.push(
-            (cromosoma.clone(),
-             clasificador_1nn_con_pesos(&datos,
-                                        &datos,
-                                        &cromosoma).2
-            )
-        );
+        hijos.push(cromosoma);
         
         if seleccionados.len() > 2 {
             i += 2;
@@ -1766,6 +1721,11 @@ fn cruce_blx<T: DataElem<T> + Copy + Clone>(
             i += 1;
         }
     }            
+
+    // Los hijos se evalúan todos a la vez, una vez generados (la
+    // evaluación no usa el generador)
+    let valores = evaluador.evaluar_poblacion(&hijos);
+    pob_provisional.extend(hijos.into_iter().zip(valores));
 }
     
 // Torneo binario
@@ -1778,7 +1738,7 @@ fn cruce_blx<T: DataElem<T> + Copy + Clone>(
 fn torneo_binario(
     candidato1: usize,
     candidato2: usize,
-    pob_evaluada: &Vec<(Vec<f32>,f32)>)
+    pob_evaluada: &[(Vec<f32>, f32)])
     -> usize {
     
     let f_1 = pob_evaluada[candidato1].1;
@@ -1786,45 +1746,36 @@ fn torneo_binario(
 
 //    println!("{}  {}", f_1, f_2);
     
-    return if f_1 >= f_2 {candidato1} else {candidato2};
+    if f_1 >= f_2 {candidato1} else {candidato2}
 }
 
-fn aux_busqueda_local<T: DataElem<T> + Copy + Clone>(
-    datos: &Vec<T>,
+// Genera un vecino de la solución cambiando un peso al azar y pasa a
+// él si mejora la función objetivo
+
+fn aux_busqueda_local(
+    evaluacion: &mut EvaluacionIncremental,
     mut rng: &mut StdRng,
-    pesos: &Vec<f32>,
-    mejor_f_obj: f32,
-    distribucion_normal: &Normal )
-    -> Vec<f32> {
-    let mut pesos_aux = pesos.clone();
-    let num_attributes = T::get_num_attributes();
+    distribucion_normal: &Normal ) {
+    let num_attributes = evaluacion.pesos().len();
     
     
     let indice_a_mejorar = rng.gen_range(0, num_attributes);
 
-    pesos_aux[indice_a_mejorar] +=
-        distribucion_normal.sample(&mut rng) as f32;
-    if pesos_aux[indice_a_mejorar] < 0.0 {
-        pesos_aux[indice_a_mejorar] = 0.0;
-    } else if pesos_aux[indice_a_mejorar] > 1.0 {
-        pesos_aux[indice_a_mejorar] = 1.0;
-    }
+    let mut peso_aux = evaluacion.pesos()[indice_a_mejorar];
+    peso_aux += distribucion_normal.sample(&mut rng) as f32;
+    peso_aux = peso_aux.clamp(0.0, 1.0);
 
-    let f_obj_actual = clasificador_1nn_con_pesos(
-        &datos,
-        &datos,
-        &pesos_aux).2;
+    let f_obj_actual =
+        evaluacion.evaluar_cambio(indice_a_mejorar, peso_aux).2;
     //println!("Mutación bl f={}", f_obj_actual);
 
-    // Si se mejora se devuelve el peso mejorado, en caso contrario se
-    // devuelve el que ya existía
-    if f_obj_actual > mejor_f_obj {
+    // Si se mejora se pasa al peso mejorado, en caso contrario se
+    // mantiene el que ya existía
+    if f_obj_actual > evaluacion.valor().2 {
 
         //  println!("Vector de pesos mejorado. F_obj: {}",
         //         f_obj_actual);
-        return pesos_aux;
-    } else {
-        return pesos.clone();
+        evaluacion.aceptar_cambio();
     }
 }
 
@@ -1835,24 +1786,22 @@ fn aux_busqueda_local<T: DataElem<T> + Copy + Clone>(
 
 fn mutacion_ils(
     solucion: Vec<f32>,
-    mut rng: &mut StdRng,
+    rng: &mut StdRng,
     num_attributes: usize)
     -> Vec<f32> {
 
     let mezclas = (0.1 * num_attributes as f32) as usize;
     let mut aux_sol = solucion.clone();
 
-    for contador_mezclas in 0..mezclas {
-        let mut indice_i = rng.gen_range(0, num_attributes);
+    for _contador_mezclas in 0..mezclas {
+        let indice_i = rng.gen_range(0, num_attributes);
         let mut indice_j = rng.gen_range(0, num_attributes);
 
         while indice_i == indice_j {
             indice_j = rng.gen_range(0, num_attributes);
         }
         //println!("Cambio {} por {}",indice_i, indice_j);
-        let aux = aux_sol[indice_i];
-        aux_sol[indice_i] = aux_sol[indice_j];
-        aux_sol[indice_j] = aux;
+        aux_sol.swap(indice_i, indice_j);
     }
 
     // Debug: Mostrar cambios
@@ -1862,384 +1811,757 @@ fn mutacion_ils(
     //     }
     // }
 
-    return aux_sol;
+    aux_sol
 }
 
 
-// Método principal: Ejecuta el código de la práctica
-
-fn execute<T: DataElem<T> + Copy + Clone>(
-    path: &str,
-    seed_u64: u64)
-    -> Result<(), Box<Error>> {
-
-    // Reads data, then works with it
-    let mut data: Vec<T> = Vec::new();
-    let mut rdr = csv::Reader::from_path(&path)?;
-
-    let mut current_id = 0;
-    for result in rdr.records() {
-        let mut aux_record = T::new();
-        let record = result?;
-
-        let mut counter = 0;
-
-        aux_record.set_id(current_id);
-
-        for field in record.iter() {
-            if counter != T::get_num_attributes() {
-                aux_record.set_attribute(counter, field.parse::<f32>().unwrap());
-            } else {
-                aux_record.set_class(field.parse::<i32>().unwrap());
-            }
-
-            counter += 1;
+// Clasifica el conjunto de validación con el k-NN y los pesos
+// obtenidos (o sin pesos si no se indican)
+//
+// Devuelve las tasas y la función objetivo, la matriz de confusión y
+// la clase predicha para cada muestra
+
+fn validar(
+    conjunto_entrenamiento: Vista,
+    conjunto_validacion: Vista,
+    pesos: Option<&[f32]>,
+    objetivo: &Objetivo,
+    knn: &Knn,
+    num_clases: usize)
+    -> ((f32, f32, f32), MatrizConfusion, Vec<i32>) {
+
+    let (predicciones, tasa_red) = match pesos {
+        Some(pesos) => {
+            let (pesos_red, n_reducidos) = reducir_pesos(pesos);
+            (knn.predecir(conjunto_entrenamiento, conjunto_validacion,
+                          Some(&pesos_red)),
+             tasa_reduccion(n_reducidos, pesos_red.len() as f32))
         }
+        // Suponemos que todos los pesos ponderan con 1 y por tanto
+        // ninguno es menor que 0.2 y se reduce
+        None => (knn.predecir(conjunto_entrenamiento, conjunto_validacion,
+                              None),
+                 0.0),
+    };
 
-        current_id += 1;
-
-        data.push(aux_record);
-    }
+    let (tasas, matriz) = evaluar_predicciones(
+        conjunto_validacion, &predicciones, tasa_red, objetivo, num_clases);
 
-    normalizar_datos(&mut data);
-
-    let particiones = crear_particiones(&data);
-
-    // Ahora definiremos los conjuntos de entrenamiento y de
-    // validación, 5 pares de conjuntos donde cada par estará formado
-    // por el 80% de los datos (4/5 particiones) para entrenamiento y
-    // 20% (1/5) para validar
+    (tasas, matriz, predicciones)
+}
 
-    for n_ejecucion in 0..NUMERO_PARTICIONES {
-        let mut conjunto_entrenamiento: Vec<T> =
-            Vec::new();
-        let mut conjunto_validacion: Vec<T> = Vec::new();
+// Texto con los resultados de un algoritmo en una partición y, si se
+// ha pedido, la matriz de confusión y la clase predicha para cada
+// muestra de validación con su etiqueta original
+
+fn texto_resultados(
+    titulo: &str,
+    conjunto_validacion: Vista,
+    resultado: &Resultado,
+    predicciones: &[i32],
+    clases: &CodificadorEtiquetas,
+    configuracion: &Configuracion)
+    -> String {
+
+    let mut texto = String::new();
+    texto.push_str(&format!("-- Resultados {}\n", titulo));
+    texto.push_str("\tT_clas\tT_red\tT_obj\tTiempo\n");
+    texto.push_str(&format!("\t{}\t{}\t{}\t{}ms\n", resultado.tasa_clas,
+                            resultado.tasa_red, resultado.f_objetivo,
+                            resultado.tiempo_ms as u64));
+
+    let matriz = &resultado.matriz;
+    texto.push_str("\tT_bal\tF1_macro\tKappa\tMCC\n");
+    texto.push_str(&format!("\t{}\t{}\t{}\t{}\n\n",
+                            matriz.exactitud_balanceada(), matriz.f1_macro(),
+                            matriz.kappa(), matriz.mcc()));
+    if configuracion.mostrar_matriz {
+        texto.push_str(&matriz.texto(clases));
+    }
 
-        for particion in 0..NUMERO_PARTICIONES {
-            if n_ejecucion != particion {
-                conjunto_entrenamiento.extend(&particiones[particion]);
-            } else {
-                conjunto_validacion = particiones[particion].clone();
-            }
+    if configuracion.mostrar_predicciones {
+        texto.push_str("\tId\tClase\tPredicción\n");
+        for (miembro, prediccion) in conjunto_validacion.iter()
+            .zip(predicciones.iter()) {
+            texto.push_str(&format!("\t{}\t{}\t{}\n", miembro.get_id(),
+                                    clases.decodificar(miembro.get_class()),
+                                    clases.decodificar(*prediccion)));
         }
-        
-        // Utilizamos el clasificador k-nn con k = 1 para evaluar
-        // nuestro algoritmo con estos conjuntos de entrenamiento y
-        // test
-
-        let mut tiempo_inicial = Instant::now();
-
-        
-         let resultados_1nn = clasificador_1nn(&conjunto_entrenamiento,
-                                               &conjunto_validacion);
-        
-        let mut tiempo_total = tiempo_inicial.elapsed().as_millis();
-
-        // Resultados
-        println!("-----------------------------------------");
-        println!("Resultados partición: {} ", n_ejecucion);
-        
-        // Muestra resultados 1nn
-        
-        println!("-- Resultados clasificador 1nn");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_1nn.0, resultados_1nn.1, resultados_1nn.2, tiempo_total);
-
-        tiempo_inicial = Instant::now();
-        
-        let pesos_relief = algoritmo_relief(&conjunto_entrenamiento);
-        let resultados_relief =
-        clasificador_1nn_con_pesos(&conjunto_entrenamiento,
-                                   &conjunto_validacion, &pesos_relief);
-
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
-
-        // Muestra resultados relief
-        
-        println!("-- Resultados clasificador RELIEF");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_relief.0, resultados_relief.1, resultados_relief.2, tiempo_total);
-        
-        // Búsqueda local
-
-        tiempo_inicial = Instant::now();
-        let pesos_inicial = vec![0.0; T::get_num_attributes()];
-        let pesos_busqueda_local =
-            busqueda_local(&conjunto_entrenamiento, seed_u64, &pesos_inicial );
-
-        let resultados_bl =
-        clasificador_1nn_con_pesos(&conjunto_entrenamiento,
-                                   &conjunto_validacion,
-                                   &pesos_busqueda_local); 
-
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
-
-        println!("-- Resultados clasificador búsqueda local");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_bl.0, resultados_bl.1, resultados_bl.2, tiempo_total);
-        
-        tiempo_inicial = Instant::now();
-        
-        let mut variante_cruce = VarianteCruce::ARIT;
-        
-        let pesos_agg = alg_genetico_generacional_elitista(&conjunto_entrenamiento,
-        seed_u64, variante_cruce);
-        let resultados_agg =
-        clasificador_1nn_con_pesos(&conjunto_entrenamiento,
-        &conjunto_validacion, &pesos_agg); 
-
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
-
-
-        println!("-- Resultados algoritmo genético generacional. Cruce aritmético. Elitista.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_agg.0, resultados_agg.1, resultados_agg.2, tiempo_total);
-        
-
-        tiempo_inicial = Instant::now();
-        
-        variante_cruce = VarianteCruce::BLX;
-        let pesos_agg_blx = alg_genetico_generacional_elitista(&conjunto_entrenamiento,
-        seed_u64, variante_cruce);
-        let resultados_agg_blx =
-        clasificador_1nn_con_pesos(&conjunto_entrenamiento,
-        &conjunto_validacion, &pesos_agg_blx); 
+        texto.push('\n');
+    }
 
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
+    texto
+}
 
+// Texto con el número de muestras de cada clase, por etiqueta
 
-        println!("-- Resultados algoritmo genético generacional. Cruce BLX. Elitista.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_agg_blx.0, resultados_agg_blx.1, resultados_agg_blx.2, tiempo_total);
+fn resumen_clases(
+    muestras: Vista,
+    clases: &CodificadorEtiquetas)
+    -> String {
 
-        tiempo_inicial = Instant::now();
-        
-
-        variante_cruce = VarianteCruce::ARIT;
-        let pesos_age =
-            alg_genetico_estacionario(
-                &conjunto_entrenamiento,
-                seed_u64,
-                variante_cruce); 
-        let resultados_age =
-            clasificador_1nn_con_pesos(&conjunto_entrenamiento,
-                                       &conjunto_validacion, &pesos_age); 
-
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
-
-        println!("-- Resultados algoritmo genético estacionario. Cruce aritmético.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_age.0, resultados_age.1, resultados_age.2, tiempo_total);
-        
-        
-        tiempo_inicial = Instant::now();
-        
-        variante_cruce = VarianteCruce::BLX;
-        let pesos_age_blx =
-            alg_genetico_estacionario(
-                &conjunto_entrenamiento,
-                seed_u64,
-                variante_cruce); 
-        let resultados_age_blx =
-            clasificador_1nn_con_pesos(&conjunto_entrenamiento,
-                                       &conjunto_validacion, &pesos_age_blx); 
-
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
-
-
-        println!("-- Resultados algoritmo genético estacionario. Cruce BLX.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_age_blx.0, resultados_age_blx.1, resultados_age_blx.2, tiempo_total);
- 
-        tiempo_inicial = Instant::now();
-        
- 
-        
-        let mut variante_memetico =
-            VarianteMemetico::TODOS_CROMOSOMAS;
-        
-        let pesos_mem_bl_todos =
-            alg_memetico(
-                &conjunto_entrenamiento,
-                seed_u64,
-                variante_memetico); 
-        let resultados_mem_bl_todos =
-            clasificador_1nn_con_pesos(&conjunto_entrenamiento,
-                                       &conjunto_validacion,
-                                       &pesos_mem_bl_todos); 
+    let mut distribucion = vec![0; clases.num_clases()];
+    for miembro in muestras.iter() {
+        distribucion[miembro.get_class() as usize] += 1;
+    }
 
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
+    clases.etiquetas().iter()
+        .zip(distribucion.iter())
+        .map(|(etiqueta, n)| format!("{} ({})", etiqueta, n))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
 
+// Ejecuta un algoritmo sobre el conjunto de entrenamiento, maximizando
+// la función objetivo del evaluador, y devuelve los pesos obtenidos
+// (ninguno en el caso del 1-NN sin pesos) y el número de evaluaciones
+// de la función objetivo que ha realizado
 
-        println!("-- Resultados algoritmo memético con BL sobre todos los cromosomas. Cruce BLX.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_mem_bl_todos.0, resultados_mem_bl_todos.1, resultados_mem_bl_todos.2, tiempo_total);
+fn ejecutar_algoritmo(
+    algoritmo: Algoritmo,
+    conjunto_entrenamiento: Vista,
+    seed_u64: u64,
+    knn: &Knn,
+    evaluador: &Evaluador)
+    -> (Option<Vec<f32>>, usize) {
+
+    let pesos = match algoritmo {
+        Algoritmo::Clasificador1nn => return (None, 0),
+        Algoritmo::Relief => algoritmo_relief(conjunto_entrenamiento,
+                                              knn),
+        Algoritmo::BusquedaLocal => {
+            let pesos_inicial = vec![0.0; evaluador.num_atributos()];
+            busqueda_local(evaluador, seed_u64, &pesos_inicial)
+        }
+        Algoritmo::AggAritmetico => alg_genetico_generacional_elitista(
+            evaluador, seed_u64, VarianteCruce::Aritmetico),
+        Algoritmo::AggBlx => alg_genetico_generacional_elitista(
+            evaluador, seed_u64, VarianteCruce::Blx),
+        Algoritmo::AgeAritmetico => alg_genetico_estacionario(
+            evaluador, seed_u64, VarianteCruce::Aritmetico),
+        Algoritmo::AgeBlx => alg_genetico_estacionario(
+            evaluador, seed_u64, VarianteCruce::Blx),
+        Algoritmo::MemeticoTodos => alg_memetico(
+            evaluador, seed_u64,
+            VarianteMemetico::TodosCromosomas),
+        Algoritmo::MemeticoAleatorio => alg_memetico(
+            evaluador, seed_u64,
+            VarianteMemetico::CromAleatorioP01),
+        Algoritmo::MemeticoMejores => alg_memetico(
+            evaluador, seed_u64,
+            VarianteMemetico::MejoresNCromosomas),
+        Algoritmo::EnfriamientoSimulado => alg_enfriamiento_simulado(
+            evaluador, seed_u64),
+        Algoritmo::Ils => alg_ils(evaluador, seed_u64),
+        Algoritmo::DiffEvRand => alg_diff_evol(
+            evaluador, seed_u64, VarianteDiffEv::Rand),
+        Algoritmo::DiffEvCurrentToBest => alg_diff_evol(
+            evaluador, seed_u64, VarianteDiffEv::CurrentToBest),
+    };
+
+    (Some(pesos), evaluador.evaluaciones())
+}
 
-        tiempo_inicial = Instant::now();
+// Prepara un fichero de datos para ejecutar sobre él los algoritmos
+// y semillas de la configuración: lo lee, lo escala y crea sus
+// particiones. Lo que se mostraría hasta empezar con los resultados
+// queda en la cabecera del experimento
 
-        variante_memetico = VarianteMemetico::CROM_ALEATORIO_P0_1;
-        let pesos_mem_bl_aleatorio =
-            alg_memetico(
-                &conjunto_entrenamiento,
-                seed_u64,
-                variante_memetico); 
-        let resultados_mem_bl_aleatorio =
-            clasificador_1nn_con_pesos(&conjunto_entrenamiento,
-                                       &conjunto_validacion,
-                                       &pesos_mem_bl_aleatorio); 
+fn preparar(
+    path: &str,
+    configuracion: &Configuracion)
+    -> Result<Experimento, Box<dyn Error>> {
 
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
+    let mut cabecera = String::new();
+    cabecera.push_str("-----------------------------------------\n");
+    cabecera.push_str(&format!("Análisis para el archivo: {}\n",
+                               datos::nombre_fichero(path)));
 
+    // Reads data, then works with it
+    let (dataset, resumen_carga) =
+        datos::leer_datos(path, configuracion.politica_ausentes)?;
+    cabecera.push_str(&format!("Relación: {} ({} atributos, {} muestras)\n",
+                               dataset.nombre, dataset.num_atributos(),
+                               dataset.muestras.len()));
+    cabecera.push_str(&format!("Carga: {}\n", resumen_carga));
+
+    let clases = dataset.clases;
+    let atributos = dataset.atributos;
+    let mut data = dataset.muestras;
+    // Los ausentes se imputan en cada partición
+    let politica_imputacion = if resumen_carga.celdas_imputadas > 0 {
+        Some(configuracion.politica_ausentes)
+    } else {
+        None
+    };
+    let todas: Vec<usize> = (0..data.len()).collect();
+    cabecera.push_str(&format!("Clases: {}\n",
+                               resumen_clases(data.vista(&todas), &clases)));
+
+    // Con menos muestras que particiones alguna quedaría sin muestras
+    // de validación
+    let num_particiones = configuracion.particiones;
+    if data.len() < num_particiones {
+        return Err(format!("hay {} muestras, no se pueden hacer {} \
+                            particiones sin dejar alguna vacía",
+                           data.len(), num_particiones).into());
+    }
 
-        println!("-- Resultados algoritmo memético con BL sobre cromosomas aleatorios con p=0.1. Cruce BLX.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_mem_bl_aleatorio.0, resultados_mem_bl_aleatorio.1, resultados_mem_bl_aleatorio.2, tiempo_total);
+    // El escalado se ajusta en cada partición con los datos de
+    // entrenamiento, salvo que se pida el modo global (todos los
+    // datos, validación incluida) para comparar con resultados
+    // anteriores
+    if configuracion.mezclar {
+        cabecera.push_str(&format!(
+            "Particiones: {} x {}, estratificadas y mezcladas\n",
+            configuracion.repeticiones, num_particiones));
+    } else {
+        cabecera.push_str(&format!(
+            "Particiones: {}, estratificadas en el orden del fichero\n",
+            num_particiones));
+    }
 
+    // Escalado tal como se anota en los resultados exportados
+    let escalado = if configuracion.escalado_global {
+        format!("{}-global", configuracion.escalado)
+    } else {
+        configuracion.escalado.to_string()
+    };
+
+    if configuracion.escalado == TipoEscalado::Ninguno {
+        cabecera.push_str("Escalado: ninguno\n");
+    } else if configuracion.escalado_global {
+        cabecera.push_str(&format!("Escalado: {} global\n",
+                                   configuracion.escalado));
+        Escalador::ajustar(configuracion.escalado, data.vista(&todas))
+            .aplicar(&mut data);
+    } else {
+        cabecera.push_str(&format!("Escalado: {} ajustado en entrenamiento\n",
+                                   configuracion.escalado));
+    }
 
-        tiempo_inicial = Instant::now();
-         
-        variante_memetico = VarianteMemetico::MEJORES_N_CROMOSOMAS;
+    // Los costes de cada clase se traducen a las clases de este
+    // conjunto de datos
+    let objetivo = configuracion.objetivo.para_clases(&clases)?;
+    let texto_objetivo = objetivo.to_string();
+    cabecera.push_str(&format!("Función objetivo: {}\n", texto_objetivo));
+    cabecera.push_str(&format!("Clasificador: {}\n", configuracion.knn));
 
-                let pesos_mem_bl_mejores =
-            alg_memetico(
-                &conjunto_entrenamiento,
-                seed_u64,
-                variante_memetico); 
-        let resultados_mem_bl_mejores =
-            clasificador_1nn_con_pesos(&conjunto_entrenamiento,
-                                       &conjunto_validacion,
-                                       &pesos_mem_bl_mejores); 
+    let escalado_particion = if configuracion.escalado !=
+        TipoEscalado::Ninguno && !configuracion.escalado_global {
+        Some(configuracion.escalado)
+    } else {
+        None
+    };
+    let mut particiones_experimento: Vec<Particion> = Vec::new();
+
+    for &seed_u64 in configuracion.semillas.iter() {
+        let mut cabecera_semilla = String::new();
+        if configuracion.semillas.len() > 1 {
+            cabecera_semilla.push_str(
+                "=========================================\n");
+            cabecera_semilla.push_str(&format!("Semilla: {}\n", seed_u64));
+        }
 
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
+        for repeticion in 0..configuracion.repeticiones {
+            // Cada repetición de cada conjunto de datos mezcla con su
+            // propio generador
+            let mut rng_particiones = StdRng::seed_from_u64(derivar_semilla(
+                seed_u64, &[componente_texto("particiones"),
+                            componente_texto(&dataset.nombre),
+                            repeticion as u64]));
+            let rng = if configuracion.mezclar {
+                Some(&mut rng_particiones)
+            } else {
+                None
+            };
+            let particiones = crear_particiones(&data, num_particiones, rng);
+
+            // Ahora definiremos los conjuntos de entrenamiento y de
+            // validación, k pares de conjuntos donde cada par estará
+            // formado por k-1 particiones para entrenamiento y la
+            // restante para validar
+
+            for n_ejecucion in 0..num_particiones {
+                let mut conjunto_entrenamiento: Vec<usize> = Vec::new();
+                let mut conjunto_validacion: Vec<usize> = Vec::new();
+
+                for (particion, filas) in particiones.iter().enumerate() {
+                    if n_ejecucion != particion {
+                        conjunto_entrenamiento.extend_from_slice(filas);
+                    } else {
+                        conjunto_validacion = filas.clone();
+                    }
+                }
 
+                // Cabecera de los resultados (con la de la semilla en
+                // su primera partición)
+                let mut cabecera_particion =
+                    std::mem::take(&mut cabecera_semilla);
+                cabecera_particion.push_str(
+                    "-----------------------------------------\n");
+                if configuracion.repeticiones > 1 {
+                    cabecera_particion.push_str(&format!(
+                        "Resultados repetición: {}, partición: {} \n",
+                        repeticion, n_ejecucion));
+                } else {
+                    cabecera_particion.push_str(&format!(
+                        "Resultados partición: {} \n", n_ejecucion));
+                }
+                cabecera_particion.push_str(&format!(
+                    "Validación: {}\n\n",
+                    resumen_clases(data.vista(&conjunto_validacion),
+                                   &clases)));
+
+                let imputador = match politica_imputacion {
+                    Some(politica) => Some(Imputador::ajustar(
+                        politica, data.vista(&conjunto_entrenamiento))
+                        .map_err(|atributo| format!(
+                            "el atributo '{}' no tiene ningún valor conocido \
+                             en el entrenamiento de la partición {}",
+                            atributos[atributo], n_ejecucion))?),
+                    None => None,
+                };
+
+                particiones_experimento.push(Particion {
+                    semilla: seed_u64,
+                    repeticion,
+                    particion: n_ejecucion,
+                    cabecera: cabecera_particion,
+                    entrenamiento: conjunto_entrenamiento,
+                    validacion: conjunto_validacion,
+                    imputador,
+                    pendientes: AtomicUsize::new(
+                        configuracion.algoritmos.len()),
+                    escaladas: Mutex::new(None),
+                    diferencias: Mutex::new(None),
+                });
+            }
+        }
+    }
 
-        println!("-- Resultados algoritmo memético con BL sobre los mejores n cromosomas. Cruce BLX.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_mem_bl_mejores.0, resultados_mem_bl_mejores.1, resultados_mem_bl_mejores.2, tiempo_total);
+    Ok(Experimento {
+        nombre: dataset.nombre,
+        clases,
+        objetivo,
+        escalado,
+        texto_objetivo,
+        cabecera,
+        muestras: Arc::new(data),
+        escalado_particion,
+        particiones: particiones_experimento,
+    })
+}
 
-         
+// Milisegundos transcurridos desde el instante indicado
 
-        //// Resultados algoritmos práctica 3
+fn milisegundos(inicio: Instant) -> f64 {
+    inicio.elapsed().as_secs_f64() * 1000.0
+}
 
-        
-        tiempo_inicial = Instant::now();
+// Ejecuta un trabajo y devuelve su resultado y el texto que se muestra
+// con él. El tiempo es el que tarda el propio trabajo, validación
+// incluida, desde que un hilo lo empieza, más su parte de lo que se
+// comparte en la partición
+
+fn ejecutar_trabajo(
+    trabajo: &Trabajo,
+    experimentos: &[Experimento],
+    configuracion: &Configuracion,
+    hilos: usize)
+    -> (Resultado, String) {
+
+    let experimento = &experimentos[trabajo.experimento];
+    let particion = &experimento.particiones[trabajo.particion];
+    let (muestras, tiempo_muestras) = particion.muestras(experimento);
+    let entrenamiento = muestras.vista(&particion.entrenamiento);
+    let validacion = muestras.vista(&particion.validacion);
+    let algoritmo = trabajo.algoritmo;
+    let num_clases = experimento.clases.num_clases();
+
+    // Semilla propia del algoritmo en esta partición
+    let semilla_algoritmo = derivar_semilla(
+        particion.semilla, &[componente_texto(&experimento.nombre),
+                             particion.repeticion as u64,
+                             particion.particion as u64,
+                             componente_texto(algoritmo.nombre())]);
+    // Los términos de los pares sólo sirven a los algoritmos que
+    // evalúan la función objetivo
+    let (diferencias, tiempo_diferencias) = if algoritmo.evalua_objetivo() {
+        particion.diferencias(entrenamiento, &configuracion.knn)
+    } else {
+        (None, 0.0)
+    };
+
+    // Las muestras escaladas y los términos de los pares se comparten
+    // entre los trabajos de la partición. La espera al cerrojo mientras
+    // otro trabajo los calcula no se cuenta, sino que el tiempo de
+    // calcularlos se reparte a partes iguales entre los trabajos que
+    // los usan. Así la suma de los tiempos de la partición lo incluye
+    // una sola vez
+    let usan_diferencias = configuracion.algoritmos.iter()
+        .filter(|algoritmo| algoritmo.evalua_objetivo())
+        .count()
+        .max(1);
+    let tiempo_compartido =
+        tiempo_muestras / configuracion.algoritmos.len() as f64 +
+        tiempo_diferencias / usan_diferencias as f64;
+    let tiempo_inicial = Instant::now();
+    let evaluador = Evaluador::new(entrenamiento, &experimento.objetivo,
+                                   &configuracion.knn, num_clases, hilos,
+                                   diferencias);
+    let (pesos, evaluaciones) = ejecutar_algoritmo(
+        algoritmo, entrenamiento, semilla_algoritmo, &configuracion.knn,
+        &evaluador);
+    let (tasas, matriz, predicciones) = validar(
+        entrenamiento, validacion, pesos.as_deref(),
+        &experimento.objetivo, &configuracion.knn, num_clases);
+    particion.terminar_trabajo();
+    let tiempo_ms = milisegundos(tiempo_inicial) + tiempo_compartido;
+
+    let resultado = Resultado {
+        algoritmo,
+        semilla: particion.semilla,
+        repeticion: particion.repeticion,
+        particion: particion.particion,
+        tasa_clas: tasas.0,
+        tasa_red: tasas.1,
+        f_objetivo: tasas.2,
+        tiempo_ms,
+        evaluaciones,
+        pesos,
+        matriz,
+    };
+    let texto = texto_resultados(algoritmo.titulo(), validacion,
+                                 &resultado, &predicciones,
+                                 &experimento.clases, configuracion);
+    (resultado, texto)
+}
 
-        let pesos_enfr_simulado =
-            alg_enfriamiento_simulado(
-                &conjunto_entrenamiento,
-                seed_u64);
+// Trabajos simultáneos e hilos por trabajo. Si no se indican, los
+// procesadores se reparten entre los trabajos simultáneos para evaluar
+// las poblaciones
+//
+// Los términos de los pares y las sumas de todos los trabajos que se
+// ejecutan a la vez deben caber en MEMORIA_MAXIMA_DIFERENCIAS, así que
+// se reducen primero los trabajos simultáneos y, si ni uno solo cabe,
+// sus hilos. Los resultados no dependen de ninguno de los dos
+
+fn limitar_concurrencia(
+    experimentos: &[Experimento],
+    configuracion: &Configuracion,
+    num_trabajos: usize)
+    -> (usize, usize) {
+
+    let evalua_objetivo = configuracion.algoritmos.iter()
+        .any(|algoritmo| algoritmo.evalua_objetivo());
+    // La de la partición que más ocupa, con los hilos indicados
+    let memoria = |hilos: usize| -> usize {
+        if !evalua_objetivo {
+            return 0;
+        }
+        experimentos.iter()
+            .flat_map(|experimento| {
+                experimento.particiones.iter().filter_map(move |particion| {
+                    let entrenamiento = experimento.muestras
+                        .vista(&particion.entrenamiento);
+                    Diferencias::memoria(entrenamiento.len(),
+                                         entrenamiento.num_atributos(),
+                                         &configuracion.knn, hilos)
+                })
+            })
+            .max()
+            .unwrap_or(0)
+    };
+    let hilos_por_trabajo = |en_paralelo: usize| {
+        configuracion.hilos.unwrap_or_else(|| {
+            (planificador::procesadores() / en_paralelo).max(1)
+        })
+    };
+
+    let mut en_paralelo = configuracion.trabajos.min(num_trabajos).max(1);
+    while en_paralelo > 1 &&
+        memoria(hilos_por_trabajo(en_paralelo)).saturating_mul(en_paralelo)
+        > MEMORIA_MAXIMA_DIFERENCIAS {
+        en_paralelo -= 1;
+    }
+    let mut hilos = hilos_por_trabajo(en_paralelo);
+    while hilos > 1 && memoria(hilos) > MEMORIA_MAXIMA_DIFERENCIAS {
+        hilos -= 1;
+    }
 
-        let resultados_enfr_simulado =
-            clasificador_1nn_con_pesos(
-                &conjunto_entrenamiento,
-                &conjunto_validacion,
-                &pesos_enfr_simulado);
+    (en_paralelo, hilos)
+}
 
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
 
-        println!("-- Resultados algoritmo enfriamiento simulado.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_enfr_simulado.0, resultados_enfr_simulado.1, resultados_enfr_simulado.2, tiempo_total);
+    let configuracion = match cli::procesar_argumentos(&args) {
+        Ok(Orden::Ejecutar(configuracion)) => configuracion,
+        Ok(Orden::ListarAlgoritmos) => {
+            for algoritmo in Algoritmo::TODOS.iter() {
+                println!("{:<14}{}", algoritmo.nombre(), algoritmo.titulo());
+            }
+            return;
+        }
+        Ok(Orden::Ayuda) => {
+            println!("{}", cli::AYUDA);
+            return;
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, cli::AYUDA);
+            process::exit(2);
+        }
+    };
+
+    println!("{}: {}",
+             if configuracion.semillas.len() > 1 {
+                 "Se usarán como semillas"
+             } else {
+                 "Se usará como semilla"
+             },
+             configuracion.semillas.iter()
+             .map(|s| s.to_string())
+             .collect::<Vec<String>>()
+             .join(", "));
+
+    let mut exportador = match configuracion.exportar {
+        Some(ref path) => match Exportador::new(path) {
+            Ok(exportador) => Some(exportador),
+            Err(err) => {
+                println!("error: {}", err);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // Se preparan todos los conjuntos de datos antes de empezar, así
+    // un error en uno de ellos no espera a que terminen los anteriores
+    let mut experimentos: Vec<Experimento> = Vec::new();
+    for path in configuracion.datos.iter() {
+        match preparar(path, &configuracion) {
+            Ok(experimento) => experimentos.push(experimento),
+            Err(err) => {
+                println!("-----------------------------------------");
+                println!("Análisis para el archivo: {}",
+                         datos::nombre_fichero(path));
+                println!("error: {}", err);
+                process::exit(1);
+            }
+        }
+    }
 
-        
-        
-        tiempo_inicial = Instant::now();
+    // Un trabajo por algoritmo en cada partición, en el orden en el
+    // que se muestran los resultados
+    let mut trabajos: Vec<Trabajo> = Vec::new();
+    for (i, experimento) in experimentos.iter().enumerate() {
+        for particion in 0..experimento.particiones.len() {
+            for &algoritmo in configuracion.algoritmos.iter() {
+                trabajos.push(Trabajo { experimento: i, particion,
+                                        algoritmo });
+            }
+        }
+    }
 
-        let pesos_ils = alg_ils(
-            &conjunto_entrenamiento,
-            seed_u64);
+    let (en_paralelo, hilos) = limitar_concurrencia(
+        &experimentos, &configuracion, trabajos.len());
+    let texto_clasificador = configuracion.knn.to_string();
+
+    let mut resumenes: Vec<Resumen> = Vec::new();
+    let ejecucion = planificador::ejecutar_en_orden(
+        &trabajos, en_paralelo,
+        |trabajo| ejecutar_trabajo(trabajo, &experimentos, &configuracion,
+                                   hilos),
+        |i, (resultado, texto)| -> Result<(), Box<dyn Error>> {
+            let trabajo = &trabajos[i];
+            let experimento = &experimentos[trabajo.experimento];
+            let anterior = i.checked_sub(1).map(|i| &trabajos[i]);
+
+            // Primer trabajo de un conjunto de datos o de una partición
+            if anterior.map(|a| a.experimento) != Some(trabajo.experimento) {
+                if let Some(resumen) = resumenes.last() {
+                    resumen.mostrar();
+                }
+                print!("{}", experimento.cabecera);
+                resumenes.push(Resumen::new(&experimento.nombre));
+            }
+            if anterior.map(|a| (a.experimento, a.particion)) !=
+                Some((trabajo.experimento, trabajo.particion)) {
+                print!("{}",
+                       experimento.particiones[trabajo.particion].cabecera);
+            }
 
-        let resultados_ils =
-            clasificador_1nn_con_pesos(
-                &conjunto_entrenamiento,
-                &conjunto_validacion,
-                &pesos_ils);
-        
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
+            print!("{}", texto);
+            if let Some(exportador) = exportador.as_mut() {
+                exportador.escribir(&experimento.nombre,
+                                    &experimento.escalado,
+                                    &experimento.texto_objetivo,
+                                    &texto_clasificador, &resultado)?;
+            }
+            if let Some(resumen) = resumenes.last_mut() {
+                resumen.anadir(resultado);
+            }
+            Ok(())
+        });
+    if let Err(err) = ejecucion {
+        println!("error: {}", err);
+        process::exit(1);
+    }
+    if let Some(resumen) = resumenes.last() {
+        resumen.mostrar();
+    }
 
-        println!("-- Resultados algoritmo ILS.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_ils.0, resultados_ils.1, resultados_ils.2, tiempo_total);
-        
-        tiempo_inicial = Instant::now();
+    if configuracion.estadistica {
+        for resumen in resumenes.iter() {
+            estadistica::mostrar_comparacion(resumen.dataset(), &[resumen]);
+        }
+        if resumenes.len() > 1 {
+            let todos: Vec<&Resumen> = resumenes.iter().collect();
+            estadistica::mostrar_comparacion("todos los conjuntos", &todos);
+        }
+    }
 
-        let variante_mutacion = VarianteDiffEv::RAND;
-            
-        let pesos_diff_evol_rand = alg_diff_evol(
-            &conjunto_entrenamiento,
-            seed_u64,
-            variante_mutacion
-        );
-
-        let resultados_diff_evol_rand =
-            clasificador_1nn_con_pesos(
-                &conjunto_entrenamiento,
-                &conjunto_validacion,
-                &pesos_diff_evol_rand);
-        
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
+    if let Some(ref path) = configuracion.informe {
+        if let Err(err) = informe::escribir_informe(path, &resumenes) {
+            println!("error: {}", err);
+            process::exit(1);
+        }
+        println!("Informe guardado en {}", path);
+    }
+}
 
-        println!("-- Resultados algoritmo Evolución Diferencial - Rand.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_diff_evol_rand.0, resultados_diff_evol_rand.1, resultados_diff_evol_rand.2, tiempo_total);
-         
-        
-        tiempo_inicial = Instant::now();
-        
-        let variante_mutacion = VarianteDiffEv::CURRENT_TO_BEST;
-            
-        let pesos_diff_evol_curr_to_best = alg_diff_evol(
-            &conjunto_entrenamiento,
-            seed_u64,
-            variante_mutacion
-        );
-
-        let resultados_diff_evol_curr_to_best =
-            clasificador_1nn_con_pesos(
-                &conjunto_entrenamiento,
-                &conjunto_validacion,
-                &pesos_diff_evol_curr_to_best);
-        
-        tiempo_total = tiempo_inicial.elapsed().as_millis();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Muestras aleatorias de dos clases, algo separadas en el primer
+    // atributo
+    fn muestras(rng: &mut StdRng, n: usize, num_atributos: usize)
+                -> Muestras {
+        let mut muestras = Muestras::new(num_atributos);
+        for i in 0..n {
+            let clase = (i % 2) as i32;
+            let atributos: Vec<f32> = (0..num_atributos)
+                .map(|a| {
+                    let centro = if a == 0 { clase as f32 * 0.4 } else { 0.5 };
+                    (centro + rng.gen_range(-0.5, 0.5)).clamp(0.0, 1.0)
+                })
+                .collect();
+            muestras.anadir(&atributos, clase);
+        }
+        muestras
+    }
 
-        println!("-- Resultados algoritmo Evolución Diferencial - Current to best.");
-        println!("\tT_clas\tT_red\tT_obj\tTiempo");
-        println!("\t{}\t{}\t{}\t{}ms\n", resultados_diff_evol_curr_to_best.0, resultados_diff_evol_curr_to_best.1, resultados_diff_evol_curr_to_best.2, tiempo_total);
+    // Cada clase sigue el turno de la anterior, así que con más
+    // particiones que muestras de cada clase no queda ninguna vacía
+    #[test]
+    fn particiones_equilibradas() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut datos = Muestras::new(1);
+        for (clase, n) in [5, 3, 4].iter().enumerate() {
+            for _ in 0..*n {
+                datos.anadir(&[0.0], clase as i32);
+            }
+        }
 
-        
+        for k in [2, 5, 7, 12].iter() {
+            for rng in [None, Some(&mut rng)] {
+                let particiones = crear_particiones(&datos, *k, rng);
+                let tamanos: Vec<usize> = particiones.iter()
+                    .map(|p| p.len())
+                    .collect();
+                let minimo = *tamanos.iter().min().unwrap();
+                let maximo = *tamanos.iter().max().unwrap();
+                assert!(minimo >= 1 && maximo - minimo <= 1,
+                        "k = {}: {:?}", k, tamanos);
+
+                let mut filas: Vec<usize> = particiones.concat();
+                filas.sort_unstable();
+                assert_eq!(filas, (0..datos.len()).collect::<Vec<usize>>());
+            }
+        }
     }
-    
-    Ok(())
-}
 
-fn main() {
-    let args: Vec<_> = env::args().collect();
-    let mut seed_u64: u64 = 4;
-    
-    if args.len() == 2 {
-        seed_u64 = args[1].parse::<u64>().unwrap();
-        println!("Se usará como semilla: {}", seed_u64);
-    } else if args.len() != 2 {
-        println!("* Formato de introducción de semilla incorrecto, se usará 4 como semilla.\nPara usar una semilla concreta utilice cargo run --release <semilla>");
-    }
-    println!("-----------------------------------------");
-    println!("Análisis para el archivo: colposcopy");
-    if let Err(err) = execute::<ColposcopyRecord>("../data/colposcopy.csv", seed_u64) {
-        println!("error: {}", err);
-        process::exit(1);
+    // Los algoritmos sólo comparan valores de la función objetivo, así
+    // que con la misma función multiplicada por 4 (valores hasta 400, o
+    // negativos si los pesos lo son) devuelven los mismos pesos
+    #[test]
+    fn age_y_de_no_dependen_de_la_escala_del_objetivo() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let muestras = muestras(&mut rng, 12, 6);
+        let filas: Vec<usize> = (0..muestras.len()).collect();
+        let datos = muestras.vista(&filas);
+        let knn = Knn::default();
+
+        let ejecutar = |objetivo: &str| {
+            let objetivo: Objetivo = objetivo.parse().unwrap();
+            let diferencias = Diferencias::calcular(datos, &knn);
+            let evaluador = Evaluador::new(datos, &objetivo, &knn, 2, 1,
+                                           diferencias.map(Arc::new));
+            vec![
+                alg_genetico_estacionario(&evaluador, 1, VarianteCruce::Blx),
+                alg_diff_evol(&evaluador, 2, VarianteDiffEv::Rand),
+                alg_diff_evol(&evaluador, 3, VarianteDiffEv::CurrentToBest),
+            ]
+        };
+
+        assert_eq!(ejecutar("0.5*clas+0.5*red"), ejecutar("2*clas+2*red"));
+        assert_eq!(ejecutar("-0.5*clas+-0.5*red"),
+                   ejecutar("-2*clas+-2*red"));
     }
-       
-    println!("-----------------------------------------");
-    println!("Análisis para el archivo: ionosphere");
-    if let Err(err) = execute::<IonosphereRecord>("../data/ionosphere.csv", seed_u64) {
-        println!("error: {}", err);
-        process::exit(1);
+
+    // Los genéticos y la evolución diferencial reparten las
+    // evaluaciones de la población entre los hilos, pero dan los mismos
+    // pesos y valores de la función objetivo con uno que con cuatro
+    #[test]
+    fn agg_y_de_no_dependen_de_los_hilos() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let muestras = muestras(&mut rng, 12, 6);
+        let filas: Vec<usize> = (0..muestras.len()).collect();
+        let datos = muestras.vista(&filas);
+        let knn = Knn::default();
+        let objetivo = Objetivo::default();
+        let diferencias = Diferencias::calcular(datos, &knn)
+            .map(Arc::new);
+
+        let ejecutar = |hilos: usize| {
+            let evaluador = Evaluador::new(datos, &objetivo, &knn, 2, hilos,
+                                           diferencias.clone());
+            let pesos = vec![
+                alg_genetico_generacional_elitista(&evaluador, 1,
+                                                   VarianteCruce::Blx),
+                alg_diff_evol(&evaluador, 2, VarianteDiffEv::Rand),
+            ];
+            let valores: Vec<(f32, f32, f32)> = pesos.iter()
+                .map(|pesos| evaluador.evaluar(pesos))
+                .collect();
+            (pesos, valores)
+        };
+
+        assert_eq!(ejecutar(1), ejecutar(4));
     }
-      
-    println!("-----------------------------------------");
-    println!("Análisis para el archivo: texture");
-    if let Err(err) = execute::<TextureRecord>("../data/texture.csv", seed_u64) {
-        println!("error: {}", err);
-        process::exit(1);
+
+    // Con pocos atributos no se espera ninguna mutación por generación,
+    // y el estacionario debe terminar igualmente al agotar las
+    // evaluaciones
+    #[test]
+    fn age_termina_con_pocos_atributos() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let muestras = muestras(&mut rng, 20, 4);
+        let filas: Vec<usize> = (0..muestras.len()).collect();
+        let datos = muestras.vista(&filas);
+        let knn = Knn::default();
+        let objetivo = Objetivo::default();
+
+        for variante in [VarianteCruce::Aritmetico, VarianteCruce::Blx] {
+            let evaluador = Evaluador::new(datos, &objetivo, &knn, 2, 1,
+                                           None);
+            let pesos = alg_genetico_estacionario(&evaluador, 1, variante);
+            assert_eq!(pesos.len(), 4);
+            // Las de la población final se hacen tras la parada
+            assert!(evaluador.evaluaciones() <=
+                    MAXIMO_EVALUACIONES_F_OBJ + 2 * TAM_POBLACION_GEN);
+        }
     }
 }
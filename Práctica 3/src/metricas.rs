@@ -0,0 +1,247 @@
+// Matriz de confusión y métricas de clasificación
+//
+// La tasa de clasificación no basta con clases desbalanceadas (en
+// colposcopy la minoritaria es un 25% de las muestras). A partir de la
+// matriz de confusión se obtienen precisión, exhaustividad y F1 por
+// clase, la tasa de clasificación balanceada, el F1 macro, la kappa de
+// Cohen y el coeficiente de correlación de Matthews (MCC, en su
+// versión multiclase). Las tasas y F1 se dan en porcentaje, como
+// T_clas; kappa y MCC entre -1 y 1.
+
+use crate::datos::Vista;
+use crate::etiquetas::CodificadorEtiquetas;
+
+#[derive(Clone, Debug)]
+pub struct MatrizConfusion {
+    num_clases: usize,
+    // Fila: clase real; columna: clase predicha
+    celdas: Vec<usize>,
+}
+
+impl MatrizConfusion {
+    pub fn new(num_clases: usize) -> MatrizConfusion {
+        MatrizConfusion { num_clases, celdas: vec![0; num_clases * num_clases] }
+    }
+
+    // Matriz de las predicciones para las muestras indicadas
+    pub fn desde_predicciones(muestras: Vista, predicciones: &[i32],
+                              num_clases: usize) -> MatrizConfusion {
+        let mut matriz = MatrizConfusion::new(num_clases);
+        for (miembro, prediccion) in muestras.iter().zip(predicciones.iter()) {
+            matriz.anadir(miembro.get_class(), *prediccion);
+        }
+        matriz
+    }
+
+    pub fn anadir(&mut self, real: i32, predicha: i32) {
+        self.celdas[real as usize * self.num_clases + predicha as usize] += 1;
+    }
+
+    pub fn num_clases(&self) -> usize {
+        self.num_clases
+    }
+
+    pub fn celda(&self, real: usize, predicha: usize) -> usize {
+        self.celdas[real * self.num_clases + predicha]
+    }
+
+    pub fn total(&self) -> usize {
+        self.celdas.iter().sum()
+    }
+
+    // Muestras de cada clase real
+    pub fn soporte(&self, clase: usize) -> usize {
+        (0..self.num_clases).map(|p| self.celda(clase, p)).sum()
+    }
+
+    // Muestras asignadas a cada clase
+    fn predichas(&self, clase: usize) -> usize {
+        (0..self.num_clases).map(|r| self.celda(r, clase)).sum()
+    }
+
+    fn aciertos(&self) -> usize {
+        (0..self.num_clases).map(|c| self.celda(c, c)).sum()
+    }
+
+    // Clases que aparecen como reales o como predichas
+    fn clases_presentes(&self) -> Vec<usize> {
+        (0..self.num_clases)
+            .filter(|&c| self.soporte(c) > 0 || self.predichas(c) > 0)
+            .collect()
+    }
+
+    pub fn precision(&self, clase: usize) -> f32 {
+        cociente(self.celda(clase, clase), self.predichas(clase)) * 100.0
+    }
+
+    pub fn exhaustividad(&self, clase: usize) -> f32 {
+        cociente(self.celda(clase, clase), self.soporte(clase)) * 100.0
+    }
+
+    pub fn f1(&self, clase: usize) -> f32 {
+        let precision = self.precision(clase);
+        let exhaustividad = self.exhaustividad(clase);
+        if precision + exhaustividad == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * exhaustividad / (precision + exhaustividad)
+        }
+    }
+
+    // Media de la exhaustividad de las clases con alguna muestra real
+    pub fn exactitud_balanceada(&self) -> f32 {
+        let clases: Vec<usize> = (0..self.num_clases)
+            .filter(|&c| self.soporte(c) > 0)
+            .collect();
+        if clases.is_empty() {
+            return 0.0;
+        }
+        clases.iter().map(|&c| self.exhaustividad(c)).sum::<f32>() /
+            clases.len() as f32
+    }
+
+    pub fn f1_macro(&self) -> f32 {
+        let clases = self.clases_presentes();
+        if clases.is_empty() {
+            return 0.0;
+        }
+        clases.iter().map(|&c| self.f1(c)).sum::<f32>() / clases.len() as f32
+    }
+
+    pub fn kappa(&self) -> f32 {
+        let n = self.total() as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        let observada = self.aciertos() as f64 / n;
+        let esperada = (0..self.num_clases)
+            .map(|c| self.soporte(c) as f64 * self.predichas(c) as f64)
+            .sum::<f64>() / (n * n);
+        if esperada == 1.0 {
+            0.0
+        } else {
+            ((observada - esperada) / (1.0 - esperada)) as f32
+        }
+    }
+
+    pub fn mcc(&self) -> f32 {
+        let s = self.total() as f64;
+        let c = self.aciertos() as f64;
+        let (mut suma_pt, mut suma_p2, mut suma_t2) = (0.0, 0.0, 0.0);
+        for clase in 0..self.num_clases {
+            let p = self.predichas(clase) as f64;
+            let t = self.soporte(clase) as f64;
+            suma_pt += p * t;
+            suma_p2 += p * p;
+            suma_t2 += t * t;
+        }
+        let denominador = ((s * s - suma_p2) * (s * s - suma_t2)).sqrt();
+        if denominador == 0.0 {
+            0.0
+        } else {
+            ((c * s - suma_pt) / denominador) as f32
+        }
+    }
+
+    // Texto con la matriz y las métricas de cada clase
+    pub fn texto(&self, clases: &CodificadorEtiquetas) -> String {
+        let mut texto = String::new();
+        texto.push_str(&format!("\tReal \\ Predicha\t{}\n",
+                                clases.etiquetas().join("\t")));
+        for real in 0..self.num_clases {
+            let fila: Vec<String> = (0..self.num_clases)
+                .map(|predicha| self.celda(real, predicha).to_string())
+                .collect();
+            texto.push_str(&format!("\t{}\t{}\n",
+                                    clases.decodificar(real as i32),
+                                    fila.join("\t")));
+        }
+        texto.push_str("\tClase\tPrecisión\tExhaust.\tF1\tSoporte\n");
+        for clase in 0..self.num_clases {
+            texto.push_str(&format!("\t{}\t{}\t{}\t{}\t{}\n",
+                                    clases.decodificar(clase as i32),
+                                    self.precision(clase),
+                                    self.exhaustividad(clase),
+                                    self.f1(clase), self.soporte(clase)));
+        }
+        texto.push('\n');
+        texto
+    }
+}
+
+fn cociente(numerador: usize, denominador: usize) -> f32 {
+    if denominador == 0 {
+        0.0
+    } else {
+        numerador as f32 / denominador as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matriz(filas: &[&[usize]]) -> MatrizConfusion {
+        let mut matriz = MatrizConfusion::new(filas.len());
+        for (real, fila) in filas.iter().enumerate() {
+            for (predicha, &veces) in fila.iter().enumerate() {
+                for _ in 0..veces {
+                    matriz.anadir(real as i32, predicha as i32);
+                }
+            }
+        }
+        matriz
+    }
+
+    fn aproximado(valor: f32, esperado: f32) {
+        assert!((valor - esperado).abs() < 1e-4,
+                "{} en lugar de {}", valor, esperado);
+    }
+
+    // Valores calculados a mano. La tercera clase no aparece, así que
+    // no cuenta en las medias
+    #[test]
+    fn metricas_de_una_matriz() {
+        let m = matriz(&[&[5, 1, 0], &[2, 2, 0], &[0, 0, 0]]);
+        assert_eq!((m.total(), m.soporte(0), m.soporte(1)), (10, 6, 4));
+
+        aproximado(m.precision(0), 500.0 / 7.0);
+        aproximado(m.exhaustividad(0), 500.0 / 6.0);
+        aproximado(m.f1(0), 1000.0 / 13.0);
+        aproximado(m.precision(1), 200.0 / 3.0);
+        aproximado(m.exhaustividad(1), 50.0);
+        aproximado(m.f1(1), 400.0 / 7.0);
+        assert_eq!((m.precision(2), m.exhaustividad(2), m.f1(2)),
+                   (0.0, 0.0, 0.0));
+
+        aproximado(m.exactitud_balanceada(), (500.0 / 6.0 + 50.0) / 2.0);
+        aproximado(m.f1_macro(), (1000.0 / 13.0 + 400.0 / 7.0) / 2.0);
+        // (0.7 - 0.54) / (1 - 0.54)
+        aproximado(m.kappa(), 0.16 / 0.46);
+        // (7·10 - 54) / √((100 - 58)·(100 - 52))
+        aproximado(m.mcc(), 16.0 / 2016f32.sqrt());
+    }
+
+    #[test]
+    fn casos_extremos() {
+        // Todo acertado
+        let m = matriz(&[&[3, 0], &[0, 2]]);
+        assert_eq!((m.kappa(), m.mcc()), (1.0, 1.0));
+        assert_eq!((m.exactitud_balanceada(), m.f1_macro()), (100.0, 100.0));
+
+        // Todo fallado entre dos clases
+        let m = matriz(&[&[0, 2], &[2, 0]]);
+        assert_eq!((m.kappa(), m.mcc()), (-1.0, -1.0));
+
+        // Todo asignado a una clase: ni kappa ni MCC la distinguen del
+        // azar
+        let m = matriz(&[&[4, 0], &[1, 0]]);
+        assert_eq!((m.kappa(), m.mcc()), (0.0, 0.0));
+        aproximado(m.exactitud_balanceada(), 50.0);
+
+        // Sin muestras
+        let m = MatrizConfusion::new(3);
+        assert_eq!((m.kappa(), m.mcc(), m.exactitud_balanceada(),
+                    m.f1_macro()), (0.0, 0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,221 @@
+// Núcleos vectorizados de las distancias
+//
+// La distancia euclídea (al cuadrado) y la de Manhattan con pesos son
+// el bucle más interno de todos los algoritmos. En x86_64 se calculan
+// con instrucciones AVX, de 8 atributos en 8, si el procesador las
+// tiene (se comprueba al ejecutar). Si no, o en otras arquitecturas,
+// se usa la versión escalar.
+//
+// La versión vectorial suma en otro orden, así que el resultado puede
+// diferir de la escalar en el redondeo de los últimos bits.
+
+// Suma de ((a - b) * peso)² para cada atributo. Sin pesos, todos
+// valen 1
+pub fn euclidea_cuadrado(atributos1: &[f32], atributos2: &[f32],
+                         pesos: Option<&[f32]>) -> f32 {
+    comprobar_longitudes(atributos1, atributos2, pesos);
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx") {
+            // Las longitudes se han comprobado y el procesador tiene AVX
+            return unsafe {
+                avx::euclidea_cuadrado(atributos1, atributos2, pesos)
+            };
+        }
+    }
+
+    escalar::euclidea_cuadrado(atributos1, atributos2, pesos)
+}
+
+// Suma de |(a - b) * peso| para cada atributo. Sin pesos, todos
+// valen 1
+pub fn manhattan(atributos1: &[f32], atributos2: &[f32],
+                 pesos: Option<&[f32]>) -> f32 {
+    comprobar_longitudes(atributos1, atributos2, pesos);
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx") {
+            // Las longitudes se han comprobado y el procesador tiene AVX
+            return unsafe { avx::manhattan(atributos1, atributos2, pesos) };
+        }
+    }
+
+    escalar::manhattan(atributos1, atributos2, pesos)
+}
+
+// Los núcleos vectoriales leen sin comprobar los límites, así que las
+// tres filas deben tener la misma longitud
+fn comprobar_longitudes(atributos1: &[f32], atributos2: &[f32],
+                        pesos: Option<&[f32]>) {
+    assert_eq!(atributos1.len(), atributos2.len());
+    if let Some(pesos) = pesos {
+        assert_eq!(atributos1.len(), pesos.len());
+    }
+}
+
+mod escalar {
+    fn diferencias<'a>(atributos1: &'a [f32], atributos2: &'a [f32],
+                       pesos: Option<&'a [f32]>)
+                       -> impl Iterator<Item = f32> + 'a {
+        atributos1.iter().zip(atributos2.iter())
+            .enumerate()
+            .map(move |(atributo, (a, b))| {
+                (a - b) * pesos.map_or(1.0, |pesos| pesos[atributo])
+            })
+    }
+
+    pub fn euclidea_cuadrado(atributos1: &[f32], atributos2: &[f32],
+                             pesos: Option<&[f32]>) -> f32 {
+        diferencias(atributos1, atributos2, pesos).map(|dif| dif * dif).sum()
+    }
+
+    pub fn manhattan(atributos1: &[f32], atributos2: &[f32],
+                     pesos: Option<&[f32]>) -> f32 {
+        diferencias(atributos1, atributos2, pesos).map(|dif| dif.abs()).sum()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx {
+    use std::arch::x86_64::*;
+
+    use super::escalar;
+
+    const ANCHO: usize = 8;
+
+    // Diferencias ponderadas de los atributos del bloque que empieza en
+    // inicio
+    #[target_feature(enable = "avx")]
+    unsafe fn diferencias(atributos1: &[f32], atributos2: &[f32],
+                          pesos: Option<&[f32]>, inicio: usize) -> __m256 {
+        let a = _mm256_loadu_ps(atributos1.as_ptr().add(inicio));
+        let b = _mm256_loadu_ps(atributos2.as_ptr().add(inicio));
+        let diferencia = _mm256_sub_ps(a, b);
+        match pesos {
+            Some(pesos) => _mm256_mul_ps(
+                diferencia, _mm256_loadu_ps(pesos.as_ptr().add(inicio))),
+            None => diferencia,
+        }
+    }
+
+    #[target_feature(enable = "avx")]
+    unsafe fn suma_horizontal(v: __m256) -> f32 {
+        let mut valores = [0.0f32; ANCHO];
+        _mm256_storeu_ps(valores.as_mut_ptr(), v);
+        valores.iter().sum()
+    }
+
+    // Los atributos que no llenan un bloque se suman con la versión
+    // escalar
+    fn resto<'a>(atributos1: &'a [f32], atributos2: &'a [f32],
+                 pesos: Option<&'a [f32]>)
+                 -> (&'a [f32], &'a [f32], Option<&'a [f32]>) {
+        let inicio = atributos1.len() - atributos1.len() % ANCHO;
+        (&atributos1[inicio..], &atributos2[inicio..],
+         pesos.map(|pesos| &pesos[inicio..]))
+    }
+
+    #[target_feature(enable = "avx")]
+    pub unsafe fn euclidea_cuadrado(atributos1: &[f32], atributos2: &[f32],
+                                    pesos: Option<&[f32]>) -> f32 {
+        let mut acumulado = _mm256_setzero_ps();
+        for inicio in (0..atributos1.len() / ANCHO).map(|i| i * ANCHO) {
+            let dif = diferencias(atributos1, atributos2, pesos, inicio);
+            acumulado = _mm256_add_ps(acumulado, _mm256_mul_ps(dif, dif));
+        }
+
+        let (a, b, p) = resto(atributos1, atributos2, pesos);
+        suma_horizontal(acumulado) + escalar::euclidea_cuadrado(a, b, p)
+    }
+
+    #[target_feature(enable = "avx")]
+    pub unsafe fn manhattan(atributos1: &[f32], atributos2: &[f32],
+                            pesos: Option<&[f32]>) -> f32 {
+        // El valor absoluto quita el bit de signo
+        let signo = _mm256_set1_ps(-0.0);
+        let mut acumulado = _mm256_setzero_ps();
+        for inicio in (0..atributos1.len() / ANCHO).map(|i| i * ANCHO) {
+            let dif = diferencias(atributos1, atributos2, pesos, inicio);
+            acumulado = _mm256_add_ps(acumulado, _mm256_andnot_ps(signo, dif));
+        }
+
+        let (a, b, p) = resto(atributos1, atributos2, pesos);
+        suma_horizontal(acumulado) + escalar::manhattan(a, b, p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+
+    // Filas y pesos aleatorios de la longitud indicada, con valores
+    // entre -1 y 1 en las filas y entre 0 y 1 en los pesos
+    fn filas(rng: &mut StdRng, longitud: usize)
+             -> (Vec<f32>, Vec<f32>, Vec<f32>) {
+        let mut fila = || (0..longitud)
+            .map(|_| rng.gen_range(-1.0, 1.0))
+            .collect::<Vec<f32>>();
+        let (a, b) = (fila(), fila());
+        let pesos = a.iter().zip(b.iter())
+            .map(|(x, y)| (x * y).abs())
+            .collect();
+        (a, b, pesos)
+    }
+
+    fn parecidos(vectorial: f32, escalar: f32) -> bool {
+        (vectorial - escalar).abs() <= 1e-5 * escalar.abs().max(1.0)
+    }
+
+    #[test]
+    fn valores_conocidos() {
+        let a = [1.0, 2.0, 3.0, 0.0, -1.0, 0.5, 2.0, 1.0, 4.0, -2.0];
+        let b = [4.0, 6.0, 3.0, 1.0, -1.0, 0.5, 1.0, 1.0, 1.0, -2.0];
+        let pesos = [1.0, 0.5, 2.0, 0.0, 1.0, 1.0, 2.0, 1.0, 1.0, 3.0];
+
+        assert_eq!(euclidea_cuadrado(&a, &b, None), 9.0 + 16.0 + 1.0 + 1.0 +
+                   9.0);
+        assert_eq!(euclidea_cuadrado(&a, &b, Some(&pesos)),
+                   9.0 + 4.0 + 4.0 + 9.0);
+        assert_eq!(manhattan(&a, &b, None), 3.0 + 4.0 + 1.0 + 1.0 + 3.0);
+        assert_eq!(manhattan(&a, &b, Some(&pesos)), 3.0 + 2.0 + 2.0 + 3.0);
+    }
+
+    #[test]
+    fn filas_vacias() {
+        assert_eq!(euclidea_cuadrado(&[], &[], None), 0.0);
+        assert_eq!(manhattan(&[], &[], Some(&[])), 0.0);
+    }
+
+    #[test]
+    fn coinciden_con_la_version_escalar() {
+        let mut rng = StdRng::seed_from_u64(1);
+        // Longitudes con y sin resto, hasta las de los conjuntos de
+        // datos (62 atributos en colposcopy)
+        for longitud in 0..=70 {
+            let (a, b, pesos) = filas(&mut rng, longitud);
+            for pesos in [None, Some(pesos.as_slice())].iter().cloned() {
+                let vectorial = euclidea_cuadrado(&a, &b, pesos);
+                let escalar = escalar::euclidea_cuadrado(&a, &b, pesos);
+                assert!(parecidos(vectorial, escalar),
+                        "euclídea, {} atributos: {} y {}", longitud,
+                        vectorial, escalar);
+
+                let vectorial = manhattan(&a, &b, pesos);
+                let escalar = escalar::manhattan(&a, &b, pesos);
+                assert!(parecidos(vectorial, escalar),
+                        "Manhattan, {} atributos: {} y {}", longitud,
+                        vectorial, escalar);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn longitudes_distintas() {
+        euclidea_cuadrado(&[1.0, 2.0], &[1.0], None);
+    }
+}
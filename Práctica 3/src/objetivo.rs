@@ -0,0 +1,287 @@
+// Función objetivo que maximizan los algoritmos
+//
+// La función original es ALPHA * T_clas + (1 - ALPHA) * T_red, pero con
+// clases desbalanceadas la tasa de clasificación premia los pesos que
+// ignoran la clase minoritaria. La función objetivo es ahora una
+// combinación lineal de términos, todos en porcentaje:
+//
+//   clas   tasa de clasificación
+//   bal    tasa de clasificación balanceada
+//   f1     F1 macro
+//   kappa  kappa de Cohen (x100)
+//   mcc    coeficiente de correlación de Matthews (x100)
+//   coste  exhaustividad media ponderada por el coste de fallar en
+//          cada clase
+//   red    tasa de reducción
+//
+// Se indica como "0.4*bal+0.4*f1+0.2*red". Un término de clasificación
+// solo (por ejemplo "bal") equivale a ALPHA * bal + (1 - ALPHA) * red.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::etiquetas::CodificadorEtiquetas;
+use crate::metricas::MatrizConfusion;
+use crate::ALPHA_F_OBJETIVO;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Termino {
+    Clasificacion,
+    Balanceada,
+    F1Macro,
+    Kappa,
+    Mcc,
+    Coste,
+    Reduccion,
+}
+
+impl Termino {
+    const TODOS: [Termino; 7] = [
+        Termino::Clasificacion, Termino::Balanceada, Termino::F1Macro,
+        Termino::Kappa, Termino::Mcc, Termino::Coste, Termino::Reduccion,
+    ];
+
+    fn nombre(&self) -> &'static str {
+        match self {
+            Termino::Clasificacion => "clas",
+            Termino::Balanceada => "bal",
+            Termino::F1Macro => "f1",
+            Termino::Kappa => "kappa",
+            Termino::Mcc => "mcc",
+            Termino::Coste => "coste",
+            Termino::Reduccion => "red",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Objetivo {
+    // Peso de cada término
+    terminos: Vec<(f32, Termino)>,
+    // Coste de fallar en cada clase, por etiqueta (1 si no se indica)
+    costes_etiquetas: Vec<(String, f32)>,
+    // Los mismos costes por clase codificada, una vez conocidas las
+    // etiquetas del conjunto de datos
+    costes: Vec<f32>,
+}
+
+impl Default for Objetivo {
+    fn default() -> Objetivo {
+        Objetivo::con_reduccion(Termino::Clasificacion)
+    }
+}
+
+impl Objetivo {
+    fn con_reduccion(termino: Termino) -> Objetivo {
+        Objetivo {
+            terminos: vec![(ALPHA_F_OBJETIVO, termino),
+                           (1.0 - ALPHA_F_OBJETIVO, Termino::Reduccion)],
+            costes_etiquetas: Vec::new(),
+            costes: Vec::new(),
+        }
+    }
+
+    pub fn usa_costes(&self) -> bool {
+        self.terminos.iter().any(|&(_, termino)| termino == Termino::Coste)
+    }
+
+    pub fn fijar_costes(&mut self, costes: Vec<(String, f32)>) {
+        self.costes_etiquetas = costes;
+    }
+
+    // Objetivo para un conjunto de datos: traduce los costes de cada
+    // etiqueta a su clase codificada
+    pub fn para_clases(&self, clases: &CodificadorEtiquetas)
+                       -> Result<Objetivo, String> {
+        let mut costes = vec![1.0; clases.num_clases()];
+        for (etiqueta, coste) in self.costes_etiquetas.iter() {
+            match clases.etiquetas().iter().position(|e| e == etiqueta) {
+                Some(clase) => costes[clase] = *coste,
+                None => return Err(format!(
+                    "la clase '{}' de los costes no aparece en los datos",
+                    etiqueta)),
+            }
+        }
+
+        Ok(Objetivo { costes, ..self.clone() })
+    }
+
+    // Valor de la función objetivo a partir de las tasas y de la
+    // matriz de confusión de las predicciones
+    pub fn valor(&self, tasa_clas: f32, tasa_red: f32,
+                 matriz: &MatrizConfusion) -> f32 {
+        self.terminos.iter()
+            .map(|&(peso, termino)| peso * match termino {
+                Termino::Clasificacion => tasa_clas,
+                Termino::Balanceada => matriz.exactitud_balanceada(),
+                Termino::F1Macro => matriz.f1_macro(),
+                Termino::Kappa => 100.0 * matriz.kappa(),
+                Termino::Mcc => 100.0 * matriz.mcc(),
+                Termino::Coste => self.exhaustividad_ponderada(matriz),
+                Termino::Reduccion => tasa_red,
+            })
+            .sum()
+    }
+
+    // Media de la exhaustividad de las clases con alguna muestra,
+    // ponderada por sus costes
+    fn exhaustividad_ponderada(&self, matriz: &MatrizConfusion) -> f32 {
+        let (mut suma, mut suma_costes) = (0.0, 0.0);
+        for clase in 0..matriz.num_clases() {
+            if matriz.soporte(clase) > 0 {
+                let coste = self.costes.get(clase).cloned().unwrap_or(1.0);
+                suma += coste * matriz.exhaustividad(clase);
+                suma_costes += coste;
+            }
+        }
+
+        if suma_costes == 0.0 {
+            0.0
+        } else {
+            suma / suma_costes
+        }
+    }
+}
+
+impl FromStr for Objetivo {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<Objetivo, String> {
+        let leer_termino = |nombre: &str| {
+            let nombre = nombre.trim().to_lowercase();
+            Termino::TODOS.iter().cloned()
+                .find(|t| t.nombre() == nombre)
+                .ok_or_else(|| format!("término de la función objetivo \
+                                        desconocido '{}'", nombre))
+        };
+
+        // Un término de clasificación solo, combinado con la reducción
+        // como en la función original
+        if !texto.contains('*') && !texto.contains('+') {
+            let termino = leer_termino(texto)?;
+            if termino == Termino::Reduccion {
+                return Err("la función objetivo no puede ser sólo la tasa \
+                            de reducción".to_string());
+            }
+            return Ok(Objetivo::con_reduccion(termino));
+        }
+
+        let mut terminos: Vec<(f32, Termino)> = Vec::new();
+        for parte in texto.split('+') {
+            let (peso, termino) = match parte.split_once('*') {
+                Some((peso, termino)) => {
+                    let peso: f32 = peso.trim().parse()
+                        .map_err(|_| format!("peso no válido en la función \
+                                              objetivo: '{}'", peso.trim()))?;
+                    (peso, leer_termino(termino)?)
+                }
+                None => (1.0, leer_termino(parte)?),
+            };
+            if !peso.is_finite() {
+                return Err(format!("peso no válido en la función objetivo: \
+                                    '{}'", peso));
+            }
+            if terminos.iter().any(|&(_, t)| t == termino) {
+                return Err(format!("término repetido en la función \
+                                    objetivo: '{}'", termino.nombre()));
+            }
+            terminos.push((peso, termino));
+        }
+
+        Ok(Objetivo { terminos, costes_etiquetas: Vec::new(),
+                      costes: Vec::new() })
+    }
+}
+
+impl fmt::Display for Objetivo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let terminos: Vec<String> = self.terminos.iter()
+            .map(|(peso, termino)| format!("{}*{}", peso, termino.nombre()))
+            .collect();
+        write!(f, "{}", terminos.join("+"))?;
+
+        if !self.costes_etiquetas.is_empty() {
+            let costes: Vec<String> = self.costes_etiquetas.iter()
+                .map(|(etiqueta, coste)| format!("{}={}", etiqueta, coste))
+                .collect();
+            write!(f, " (costes {})", costes.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leer(texto: &str) -> Objetivo {
+        texto.parse().unwrap_or_else(|err| panic!("'{}': {}", texto, err))
+    }
+
+    fn error(texto: &str) -> String {
+        match texto.parse::<Objetivo>() {
+            Err(err) => err,
+            Ok(objetivo) => panic!("'{}' debería fallar: {}", texto, objetivo),
+        }
+    }
+
+    #[test]
+    fn lee_combinaciones_lineales() {
+        assert_eq!(leer("0.4*bal+0.4*f1+0.2*red").terminos,
+                   vec![(0.4, Termino::Balanceada), (0.4, Termino::F1Macro),
+                        (0.2, Termino::Reduccion)]);
+        // Sin peso vale 1; se admiten espacios, mayúsculas y pesos
+        // negativos
+        assert_eq!(leer(" -2 * Kappa + mcc ").terminos,
+                   vec![(-2.0, Termino::Kappa), (1.0, Termino::Mcc)]);
+        // Un término solo se combina con la reducción
+        assert_eq!(leer("coste").terminos,
+                   vec![(ALPHA_F_OBJETIVO, Termino::Coste),
+                        (1.0 - ALPHA_F_OBJETIVO, Termino::Reduccion)]);
+        assert_eq!(leer("clas").terminos, Objetivo::default().terminos);
+
+        // Se vuelve a leer igual desde el texto que se muestra
+        let objetivo = leer("0.75*clas+0.25*red");
+        assert_eq!(objetivo.to_string(), "0.75*clas+0.25*red");
+        assert_eq!(leer(&objetivo.to_string()).terminos, objetivo.terminos);
+    }
+
+    #[test]
+    fn errores() {
+        assert!(error("red").contains("sólo la tasa de reducción"));
+        assert!(error("exactitud").contains("desconocido"));
+        assert!(error("0.5*clas+0.5*nada").contains("desconocido"));
+        assert!(error("x*clas+red").contains("peso no válido"));
+        assert!(error("inf*clas+red").contains("peso no válido"));
+        assert!(error("NaN*clas+red").contains("peso no válido"));
+        assert!(error("clas+0.5*clas").contains("repetido"));
+        assert!(error("clas+").contains("desconocido"));
+    }
+
+    // La exhaustividad de cada clase se pondera por su coste, y las
+    // clases sin muestras no cuentan
+    #[test]
+    fn valor_con_costes() {
+        let mut matriz = MatrizConfusion::new(3);
+        for &(real, predicha) in [(0, 0), (0, 0), (0, 1), (0, 1),
+                                  (1, 1), (1, 1)].iter() {
+            matriz.anadir(real, predicha);
+        }
+        let clases = CodificadorEtiquetas::con_etiquetas(
+            &["a".to_string(), "b".to_string(), "c".to_string()]);
+
+        let mut objetivo = leer("coste+0.5*red");
+        assert!(objetivo.usa_costes());
+        objetivo.fijar_costes(vec![("b".to_string(), 3.0),
+                                   ("c".to_string(), 10.0)]);
+        let objetivo = objetivo.para_clases(&clases).unwrap();
+        // (1·50 + 3·100) / 4 + 0.5·20
+        assert_eq!(objetivo.valor(66.0, 20.0, &matriz), 87.5 + 10.0);
+
+        let mut desconocida = leer("coste");
+        desconocida.fijar_costes(vec![("d".to_string(), 2.0)]);
+        assert!(desconocida.para_clases(&clases).unwrap_err()
+                .contains("'d'"));
+        assert!(!leer("bal").usa_costes());
+    }
+}
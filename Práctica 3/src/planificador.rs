@@ -0,0 +1,69 @@
+// Planificador de trabajos
+//
+// Las ejecuciones de cada conjunto de datos, semilla, repetición,
+// partición y algoritmo son independientes entre sí. Se reparten entre
+// varios hilos, que toman el siguiente trabajo pendiente en cuanto
+// terminan el anterior. Los resultados se entregan en el orden de los
+// trabajos a medida que están disponibles, así que la salida es la
+// misma que si se ejecutaran uno tras otro.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+// Número de procesadores disponibles
+pub fn procesadores() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+// Ejecuta los trabajos con el número de hilos indicado y pasa cada
+// resultado a recibir, junto con el índice de su trabajo, en el orden
+// de los trabajos. Recibir se llama desde el hilo que llama a esta
+// función. Si devuelve un error no se empiezan más trabajos y se
+// devuelve ese error
+pub fn ejecutar_en_orden<T, R, E, F, G>(trabajos: &[T], hilos: usize,
+                                        ejecutar: F, mut recibir: G)
+                                        -> Result<(), E>
+    where T: Sync,
+          R: Send,
+          F: Fn(&T) -> R + Sync,
+          G: FnMut(usize, R) -> Result<(), E> {
+    let hilos = hilos.clamp(1, trabajos.len().max(1));
+    let siguiente = AtomicUsize::new(0);
+    let cancelado = AtomicBool::new(false);
+    let (emisor, receptor) = mpsc::channel();
+
+    thread::scope(|s| {
+        for _ in 0..hilos {
+            let emisor = emisor.clone();
+            let (siguiente, cancelado, ejecutar) =
+                (&siguiente, &cancelado, &ejecutar);
+            s.spawn(move || {
+                while !cancelado.load(Ordering::Relaxed) {
+                    let i = siguiente.fetch_add(1, Ordering::Relaxed);
+                    if i >= trabajos.len() ||
+                        emisor.send((i, ejecutar(&trabajos[i]))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(emisor);
+
+        // Resultados que han llegado antes que alguno anterior
+        let mut pendientes: BTreeMap<usize, R> = BTreeMap::new();
+        let mut proximo = 0;
+        for (i, resultado) in receptor.iter() {
+            pendientes.insert(i, resultado);
+            while let Some(resultado) = pendientes.remove(&proximo) {
+                if let Err(err) = recibir(proximo, resultado) {
+                    cancelado.store(true, Ordering::Relaxed);
+                    return Err(err);
+                }
+                proximo += 1;
+            }
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,200 @@
+// Resumen de los resultados de un conjunto de datos
+//
+// Se guardan los resultados de cada algoritmo en cada partición (y
+// semilla) y al terminar se muestran la media, la desviación típica,
+// el mínimo y el máximo de cada medida por algoritmo.
+
+use crate::metricas::MatrizConfusion;
+use crate::Algoritmo;
+
+// Resultado de un algoritmo en una partición, con los pesos
+// obtenidos (ninguno para el 1-NN sin pesos), las evaluaciones de la
+// función objetivo empleadas y la matriz de confusión en validación
+#[derive(Clone, Debug)]
+pub struct Resultado {
+    pub algoritmo: Algoritmo,
+    pub semilla: u64,
+    pub repeticion: usize,
+    pub particion: usize,
+    pub tasa_clas: f32,
+    pub tasa_red: f32,
+    pub f_objetivo: f32,
+    pub tiempo_ms: f64,
+    pub evaluaciones: usize,
+    pub pesos: Option<Vec<f32>>,
+    pub matriz: MatrizConfusion,
+}
+
+// Estadísticos de una serie de valores. La desviación típica es la
+// muestral (n - 1), 0 si sólo hay un valor
+#[derive(Clone, Copy, Debug)]
+pub struct Estadisticas {
+    pub media: f64,
+    pub desviacion: f64,
+    pub minimo: f64,
+    pub maximo: f64,
+}
+
+impl Estadisticas {
+    pub fn calcular(valores: &[f64]) -> Estadisticas {
+        let n = valores.len() as f64;
+        let media = valores.iter().sum::<f64>() / n;
+        let desviacion = if valores.len() > 1 {
+            (valores.iter().map(|v| (v - media) * (v - media)).sum::<f64>()
+             / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+
+        Estadisticas {
+            media,
+            desviacion,
+            minimo: valores.iter().cloned().fold(f64::INFINITY, f64::min),
+            maximo: valores.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+// Medidas que se resumen, con el nombre con el que se muestran
+type Medida = fn(&Resultado) -> f64;
+
+const MEDIDAS: [(&str, Medida); 8] = [
+    ("T_clas", |r| r.tasa_clas as f64),
+    ("T_red", |r| r.tasa_red as f64),
+    ("T_obj", |r| r.f_objetivo as f64),
+    ("Tiempo", |r| r.tiempo_ms),
+    ("T_bal", |r| r.matriz.exactitud_balanceada() as f64),
+    ("F1_macro", |r| r.matriz.f1_macro() as f64),
+    ("Kappa", |r| r.matriz.kappa() as f64),
+    ("MCC", |r| r.matriz.mcc() as f64),
+];
+
+pub struct Resumen {
+    dataset: String,
+    resultados: Vec<Resultado>,
+}
+
+impl Resumen {
+    pub fn new(dataset: &str) -> Resumen {
+        Resumen { dataset: dataset.to_string(), resultados: Vec::new() }
+    }
+
+    pub fn dataset(&self) -> &str {
+        &self.dataset
+    }
+
+    pub fn anadir(&mut self, resultado: Resultado) {
+        self.resultados.push(resultado);
+    }
+
+    // Algoritmos con resultados, en el orden en que se ejecutaron
+    pub fn algoritmos(&self) -> Vec<Algoritmo> {
+        let mut algoritmos: Vec<Algoritmo> = Vec::new();
+        for resultado in self.resultados.iter() {
+            if !algoritmos.contains(&resultado.algoritmo) {
+                algoritmos.push(resultado.algoritmo);
+            }
+        }
+        algoritmos
+    }
+
+    pub fn resultados_de(&self, algoritmo: Algoritmo) -> Vec<&Resultado> {
+        self.resultados.iter()
+            .filter(|r| r.algoritmo == algoritmo)
+            .collect()
+    }
+
+    // Semillas con resultados, en el orden en que se ejecutaron
+    pub fn semillas(&self) -> Vec<u64> {
+        let mut semillas: Vec<u64> = Vec::new();
+        for resultado in self.resultados.iter() {
+            if !semillas.contains(&resultado.semilla) {
+                semillas.push(resultado.semilla);
+            }
+        }
+        semillas
+    }
+
+    // Muestra una tabla con los estadísticos de cada medida para cada
+    // algoritmo. Si hay varias semillas muestra también la media de
+    // cada una y su variabilidad
+    pub fn mostrar(&self) {
+        println!("=========================================");
+        println!("Resumen: {}", self.dataset);
+        println!("\tAlgoritmo\tMedida\tMedia\tDesv.\tMín.\tMáx.\tN");
+
+        for algoritmo in self.algoritmos() {
+            let resultados = self.resultados_de(algoritmo);
+            let valores: Vec<Vec<f64>> = MEDIDAS.iter()
+                .map(|(_, valor)| resultados.iter().map(|r| valor(r)).collect())
+                .collect();
+            mostrar_estadisticas(algoritmo, &valores);
+        }
+        println!();
+
+        if self.semillas().len() > 1 {
+            self.mostrar_semillas();
+        }
+    }
+
+    // Medias de cada semilla por algoritmo y estadísticos de esas
+    // medias, que reflejan la variabilidad entre ejecuciones
+    fn mostrar_semillas(&self) {
+        let semillas = self.semillas();
+
+        println!("-- Medias por semilla");
+        let nombres_medidas: Vec<&str> = MEDIDAS.iter().map(|m| m.0).collect();
+        println!("\tAlgoritmo\tSemilla\t{}", nombres_medidas.join("\t"));
+        let mut medias_algoritmos: Vec<(Algoritmo, Vec<Vec<f64>>)> = Vec::new();
+        for algoritmo in self.algoritmos() {
+            let resultados = self.resultados_de(algoritmo);
+            let mut medias = vec![Vec::new(); MEDIDAS.len()];
+            for &semilla in semillas.iter() {
+                let de_semilla: Vec<&&Resultado> = resultados.iter()
+                    .filter(|r| r.semilla == semilla)
+                    .collect();
+                if de_semilla.is_empty() {
+                    continue;
+                }
+                let fila: Vec<f64> = MEDIDAS.iter()
+                    .map(|(_, valor)| {
+                        let valores: Vec<f64> = de_semilla.iter()
+                            .map(|r| valor(r))
+                            .collect();
+                        Estadisticas::calcular(&valores).media
+                    })
+                    .collect();
+                let celdas: Vec<String> = fila.iter()
+                    .map(|valor| format!("{:.4}", valor))
+                    .collect();
+                println!("\t{}\t{}\t{}", algoritmo.nombre(), semilla,
+                         celdas.join("\t"));
+                for (media, valor) in medias.iter_mut().zip(fila) {
+                    media.push(valor);
+                }
+            }
+            medias_algoritmos.push((algoritmo, medias));
+        }
+        println!();
+
+        println!("-- Variabilidad entre semillas (sobre las medias de cada semilla)");
+        println!("\tAlgoritmo\tMedida\tMedia\tDesv.\tMín.\tMáx.\tN");
+        for (algoritmo, medias) in medias_algoritmos.iter() {
+            mostrar_estadisticas(*algoritmo, medias);
+        }
+        println!();
+    }
+}
+
+// Filas de la tabla de estadísticos de un algoritmo: una por medida,
+// con los valores de cada una
+
+fn mostrar_estadisticas(algoritmo: Algoritmo, valores: &[Vec<f64>]) {
+    for (i, ((medida, _), valores)) in MEDIDAS.iter().zip(valores).enumerate() {
+        let e = Estadisticas::calcular(valores);
+        let nombre_algoritmo = if i == 0 { algoritmo.nombre() } else { "" };
+        println!("\t{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{:.4}\t{}",
+                 nombre_algoritmo, medida, e.media, e.desviacion,
+                 e.minimo, e.maximo, valores.len());
+    }
+}
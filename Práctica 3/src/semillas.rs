@@ -0,0 +1,80 @@
+// Derivación de semillas
+//
+// A partir de la semilla de cada ejecución se obtiene una semilla
+// propia para cada uso del generador aleatorio: particiones de cada
+// conjunto de datos y repetición, cada algoritmo en cada partición y
+// cada llamada interna (por ejemplo, cada búsqueda local del ILS). Así
+// los resultados siguen siendo reproducibles, pero los algoritmos no
+// comparten la misma secuencia de números aleatorios.
+//
+// La mezcla es la función de finalización de SplitMix64, aplicada
+// sucesivamente a la semilla y a cada componente.
+
+const INCREMENTO_SPLITMIX: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// Base y primo de FNV-1a (64 bits)
+const BASE_FNV: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIMO_FNV: u64 = 0x0000_0100_0000_01b3;
+
+// Un paso de SplitMix64
+
+fn splitmix64(estado: u64) -> u64 {
+    let mut z = estado.wrapping_add(INCREMENTO_SPLITMIX);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Semilla derivada de la semilla base y de los componentes indicados.
+// El orden de los componentes importa
+
+pub fn derivar_semilla(semilla: u64, componentes: &[u64]) -> u64 {
+    componentes.iter()
+        .fold(splitmix64(semilla), |estado, &componente| {
+            splitmix64(estado ^ splitmix64(componente))
+        })
+}
+
+// Componente a partir de un texto (nombre de conjunto de datos,
+// algoritmo, etc.), estable entre ejecuciones y plataformas
+
+pub fn componente_texto(texto: &str) -> u64 {
+    texto.bytes().fold(BASE_FNV, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIMO_FNV)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derivacion_determinista() {
+        assert_eq!(derivar_semilla(7, &[1, 2, 3]),
+                   derivar_semilla(7, &[1, 2, 3]));
+        assert_ne!(derivar_semilla(7, &[1, 2, 3]),
+                   derivar_semilla(8, &[1, 2, 3]));
+        // Sin componentes sólo se mezcla la semilla. El primer valor de
+        // SplitMix64 con estado 0 es conocido
+        assert_eq!(derivar_semilla(0, &[]), 0xe220_a839_7b1d_cdaf);
+    }
+
+    #[test]
+    fn el_orden_de_los_componentes_importa() {
+        assert_ne!(derivar_semilla(7, &[1, 2]), derivar_semilla(7, &[2, 1]));
+        assert_ne!(derivar_semilla(7, &[1]), derivar_semilla(7, &[1, 0]));
+        let nombre = componente_texto("ionosphere");
+        let algoritmo = componente_texto("relief");
+        assert_ne!(derivar_semilla(7, &[nombre, algoritmo]),
+                   derivar_semilla(7, &[algoritmo, nombre]));
+    }
+
+    // Valores de referencia de FNV-1a de 64 bits, así las semillas no
+    // cambian entre versiones ni plataformas
+    #[test]
+    fn componente_texto_estable() {
+        assert_eq!(componente_texto(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(componente_texto("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(componente_texto("foobar"), 0x8594_4171_f739_67e8);
+    }
+}